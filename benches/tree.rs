@@ -0,0 +1,100 @@
+//! Root and proof computation over large stores.
+//!
+//! Run with `cargo bench --features parallel` to compare the layouts on all the cores, and to
+//! see how the root recomputation of an audit scales with the number of threads.
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use htree_challenge::tree::*;
+
+const LAYOUTS: [Layout; 3] = [Layout::BitReversed, Layout::Rfc6962, Layout::Mmr];
+const SIZES: [usize; 3] = [1 << 12, 1 << 16, 1 << 20];
+
+fn hashes(size: usize) -> Vec<blake3::Hash> {
+    (0..size).map(|i| blake3::hash(&i.to_le_bytes())).collect()
+}
+
+fn store(layout: Layout, size: usize) -> HMap<usize> {
+    HMap::from_leaves(layout, hashes(size).into_iter().zip(0..))
+}
+
+fn root(c: &mut Criterion) {
+    let mut group = c.benchmark_group("root");
+    group.sample_size(10);
+    for layout in LAYOUTS {
+        for size in SIZES {
+            let store = store(layout, size);
+            group.throughput(Throughput::Elements(size as u64));
+            group.bench_with_input(
+                BenchmarkId::new(format!("{:?}", layout), size),
+                &store,
+                |b, store| b.iter(|| store.root()),
+            );
+        }
+    }
+    group.finish();
+}
+
+// The recomputation of an audit, from the hashes of the downloaded files.
+fn root_of(c: &mut Criterion) {
+    let mut group = c.benchmark_group("root_of");
+    group.sample_size(10);
+    for layout in LAYOUTS {
+        for size in SIZES {
+            let hashes = hashes(size);
+            group.throughput(Throughput::Elements(size as u64));
+            group.bench_with_input(
+                BenchmarkId::new(format!("{:?}", layout), size),
+                &hashes,
+                |b, hashes| b.iter(|| layout.root_of(hashes)),
+            );
+        }
+    }
+    group.finish();
+}
+
+fn proofs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("proofs");
+    group.sample_size(10);
+    let size = 1 << 16;
+    let indices: Vec<usize> = (0..size).step_by(64).collect();
+    for layout in LAYOUTS {
+        let store = store(layout, size);
+        group.throughput(Throughput::Elements(indices.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new(format!("{:?}", layout), size),
+            &store,
+            |b, store| b.iter(|| store.proofs(&indices)),
+        );
+    }
+    group.finish();
+}
+
+// The audit of a million elements with 1, 2, 4... threads.
+#[cfg(feature = "parallel")]
+fn scaling(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scaling");
+    group.sample_size(10);
+    let size = 1 << 20;
+    let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let hashes = hashes(size);
+    for layout in LAYOUTS {
+        let threads = std::iter::successors(Some(1), |n| Some(n * 2)).take_while(|n| *n <= cpus);
+        for threads in threads {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            group.bench_with_input(
+                BenchmarkId::new(format!("{:?}", layout), threads),
+                &hashes,
+                |b, hashes| b.iter(|| pool.install(|| layout.root_of(hashes))),
+            );
+        }
+    }
+    group.finish();
+}
+
+#[cfg(feature = "parallel")]
+criterion_group!(benches, root, root_of, proofs, scaling);
+#[cfg(not(feature = "parallel"))]
+criterion_group!(benches, root, root_of, proofs);
+criterion_main!(benches);
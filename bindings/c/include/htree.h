@@ -0,0 +1,158 @@
+#ifndef HTREE_H
+#define HTREE_H
+
+/* Generated by cbindgen from src/lib.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// How the elements of a store are placed in its tree, see `Layout`.
+typedef enum HtreeLayout {
+  HTREE_LAYOUT_BIT_REVERSED,
+  HTREE_LAYOUT_RFC6962,
+  HTREE_LAYOUT_MMR,
+} HtreeLayout;
+
+// The result of the functions which can fail. The verification errors are the ones of
+// `VerifyError`.
+typedef enum HtreeStatus {
+  HTREE_STATUS_OK,
+  // A pointer argument is null.
+  HTREE_STATUS_NULL_POINTER,
+  // The store has no root.
+  HTREE_STATUS_EMPTY_STORE,
+  // The proof is about another element.
+  HTREE_STATUS_WRONG_INDEX,
+  // The element is beyond the size of the store.
+  HTREE_STATUS_INDEX_OUT_OF_RANGE,
+  // The proof was made for a store of another size.
+  HTREE_STATUS_WRONG_SIZE,
+  // The proof doesn't hold the size of the store, which its layout needs.
+  HTREE_STATUS_MISSING_SIZE,
+  // The proof doesn't have the number of hashes of a proof of this element at this size.
+  HTREE_STATUS_WRONG_LENGTH,
+  // The element and the proof don't give the root.
+  HTREE_STATUS_ROOT_MISMATCH,
+  // The root before the push given by the proof is not the expected one.
+  HTREE_STATUS_PREVIOUS_ROOT_MISMATCH,
+  // The proof was made for a store of another layout.
+  HTREE_STATUS_WRONG_LAYOUT,
+} HtreeStatus;
+
+// A proof of an element of a store.
+typedef struct HtreeProof HtreeProof;
+
+// A store of hashes: a `HMap` whose elements are their hashes only.
+typedef struct HtreeStore HtreeStore;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Hash the `len` bytes of `data` to the 32 bytes of `hash`: the hash of an element to push.
+//
+// # Safety
+//
+// `data` must point to `len` bytes, or be null when `len` is 0, and `hash` to 32 writable
+// bytes.
+enum HtreeStatus htree_hash(const uint8_t *data, size_t len, uint8_t *hash);
+
+// Create an empty store placing its elements with `layout`. Free it with [htree_store_free].
+struct HtreeStore *htree_store_new(enum HtreeLayout layout);
+
+// Free a store.
+//
+// # Safety
+//
+// `store` must be null or returned by [htree_store_new], and not be used after.
+void htree_store_free(struct HtreeStore *store);
+
+// The number of elements of the store, 0 if it's null.
+//
+// # Safety
+//
+// `store` must be null or a live store.
+size_t htree_store_len(const struct HtreeStore *store);
+
+// Push the element of hash `hash` and return its proof, null if an argument is null. Free the
+// proof with [htree_proof_free].
+//
+// # Safety
+//
+// `store` must be null or a live store, and `hash` null or a pointer to 32 bytes.
+struct HtreeProof *htree_store_push(struct HtreeStore *store, const uint8_t *hash);
+
+// Write the root of the store to the 32 bytes of `root`.
+//
+// # Safety
+//
+// `store` must be null or a live store, and `root` null or a pointer to 32 writable bytes.
+enum HtreeStatus htree_store_root(const struct HtreeStore *store, uint8_t *root);
+
+// The proof of the `nth` element, null if there is none. Free it with [htree_proof_free].
+//
+// # Safety
+//
+// `store` must be null or a live store.
+struct HtreeProof *htree_store_proof(const struct HtreeStore *store, size_t nth);
+
+// The proof of the `nth` element as it was when the store had `size` elements, null if there is
+// none. Free it with [htree_proof_free].
+//
+// # Safety
+//
+// `store` must be null or a live store.
+struct HtreeProof *htree_store_proof_at(const struct HtreeStore *store, size_t nth, size_t size);
+
+// Free a proof.
+//
+// # Safety
+//
+// `proof` must be null or a proof returned by this library, and not be used after.
+void htree_proof_free(struct HtreeProof *proof);
+
+// Decode a proof from the `len` bytes of its binary encoding, null if they are not one. Free it
+// with [htree_proof_free].
+//
+// # Safety
+//
+// `bytes` must point to `len` bytes, or be null.
+struct HtreeProof *htree_proof_from_bytes(const uint8_t *bytes, size_t len);
+
+// The length of the binary encoding of the proof, written to `out` if it fits in its
+// `capacity` bytes. 0 if the proof is null.
+//
+// # Safety
+//
+// `proof` must be null or a live proof, and `out` null or a pointer to `capacity` writable
+// bytes.
+size_t htree_proof_to_bytes(const struct HtreeProof *proof, uint8_t *out, size_t capacity);
+
+// The index of the element of the proof, 0 if it's null.
+//
+// # Safety
+//
+// `proof` must be null or a live proof.
+size_t htree_proof_nth(const struct HtreeProof *proof);
+
+// Check the element of hash `hash` is the `nth` of the store of `size` elements with the root
+// `root`, whose elements are placed with `layout`.
+//
+// # Safety
+//
+// `proof` must be null or a live proof, `hash` and `root` null or pointers to 32 bytes.
+enum HtreeStatus htree_proof_verify(const struct HtreeProof *proof,
+                                    const uint8_t *hash,
+                                    size_t nth,
+                                    size_t size,
+                                    const uint8_t *root,
+                                    enum HtreeLayout layout);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* HTREE_H */
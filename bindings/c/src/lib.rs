@@ -0,0 +1,291 @@
+//! C bindings of the stores and of the proof verification, declared in `include/htree.h`.
+//!
+//! Stores and proofs are opaque handles, created by the functions of this module and freed by
+//! [htree_store_free] and [htree_proof_free]. Hashes are arrays of 32 bytes, the hash of an
+//! element being the BLAKE3 hash of its data (see [htree_hash]).
+use htree_challenge::tree::{HMap, Layout, Proof, VerifyError};
+use std::{ptr, slice};
+
+/// A store of hashes: a `HMap` whose elements are their hashes only.
+pub struct HtreeStore(HMap<()>);
+
+/// A proof of an element of a store.
+pub struct HtreeProof(Proof);
+
+/// How the elements of a store are placed in its tree, see `Layout`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HtreeLayout {
+    BitReversed,
+    Rfc6962,
+    Mmr,
+}
+
+/// The result of the functions which can fail. The verification errors are the ones of
+/// `VerifyError`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HtreeStatus {
+    Ok,
+    /// A pointer argument is null.
+    NullPointer,
+    /// The store has no root.
+    EmptyStore,
+    /// The proof is about another element.
+    WrongIndex,
+    /// The element is beyond the size of the store.
+    IndexOutOfRange,
+    /// The proof was made for a store of another size.
+    WrongSize,
+    /// The proof doesn't hold the size of the store, which its layout needs.
+    MissingSize,
+    /// The proof doesn't have the number of hashes of a proof of this element at this size.
+    WrongLength,
+    /// The element and the proof don't give the root.
+    RootMismatch,
+    /// The root before the push given by the proof is not the expected one.
+    PreviousRootMismatch,
+    /// The proof was made for a store of another layout.
+    WrongLayout,
+}
+
+impl From<HtreeLayout> for Layout {
+    fn from(layout: HtreeLayout) -> Self {
+        match layout {
+            HtreeLayout::BitReversed => Layout::BitReversed,
+            HtreeLayout::Rfc6962 => Layout::Rfc6962,
+            HtreeLayout::Mmr => Layout::Mmr,
+        }
+    }
+}
+
+impl From<VerifyError> for HtreeStatus {
+    fn from(error: VerifyError) -> Self {
+        match error {
+            VerifyError::WrongIndex { .. } => HtreeStatus::WrongIndex,
+            VerifyError::IndexOutOfRange { .. } => HtreeStatus::IndexOutOfRange,
+            VerifyError::WrongSize { .. } => HtreeStatus::WrongSize,
+            VerifyError::MissingSize => HtreeStatus::MissingSize,
+            VerifyError::WrongLength { .. } => HtreeStatus::WrongLength,
+            VerifyError::RootMismatch { .. } => HtreeStatus::RootMismatch,
+            VerifyError::PreviousRootMismatch { .. } => HtreeStatus::PreviousRootMismatch,
+            VerifyError::WrongLayout { .. } => HtreeStatus::WrongLayout,
+        }
+    }
+}
+
+// Read the 32 bytes hash at `hash`, `None` if it's null.
+unsafe fn read_hash(hash: *const u8) -> Option<blake3::Hash> {
+    (!hash.is_null()).then(|| blake3::Hash::from_bytes(*hash.cast::<[u8; 32]>()))
+}
+
+// Write `hash` to the 32 bytes at `out`, which isn't null.
+unsafe fn write_hash(hash: blake3::Hash, out: *mut u8) {
+    ptr::copy_nonoverlapping(hash.as_bytes().as_ptr(), out, 32);
+}
+
+// Move `proof` to a new handle.
+fn proof_handle(proof: Option<Proof>) -> *mut HtreeProof {
+    match proof {
+        Some(proof) => Box::into_raw(Box::new(HtreeProof(proof))),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Hash the `len` bytes of `data` to the 32 bytes of `hash`: the hash of an element to push.
+///
+/// # Safety
+///
+/// `data` must point to `len` bytes, or be null when `len` is 0, and `hash` to 32 writable
+/// bytes.
+#[no_mangle]
+pub unsafe extern "C" fn htree_hash(data: *const u8, len: usize, hash: *mut u8) -> HtreeStatus {
+    if (data.is_null() && len > 0) || hash.is_null() {
+        return HtreeStatus::NullPointer;
+    }
+    let data = if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len)
+    };
+    write_hash(blake3::hash(data), hash);
+    HtreeStatus::Ok
+}
+
+/// Create an empty store placing its elements with `layout`. Free it with [htree_store_free].
+#[no_mangle]
+pub extern "C" fn htree_store_new(layout: HtreeLayout) -> *mut HtreeStore {
+    Box::into_raw(Box::new(HtreeStore(HMap::with_layout(layout.into()))))
+}
+
+/// Free a store.
+///
+/// # Safety
+///
+/// `store` must be null or returned by [htree_store_new], and not be used after.
+#[no_mangle]
+pub unsafe extern "C" fn htree_store_free(store: *mut HtreeStore) {
+    if !store.is_null() {
+        drop(Box::from_raw(store));
+    }
+}
+
+/// The number of elements of the store, 0 if it's null.
+///
+/// # Safety
+///
+/// `store` must be null or a live store.
+#[no_mangle]
+pub unsafe extern "C" fn htree_store_len(store: *const HtreeStore) -> usize {
+    store.as_ref().map_or(0, |store| store.0.len())
+}
+
+/// Push the element of hash `hash` and return its proof, null if an argument is null. Free the
+/// proof with [htree_proof_free].
+///
+/// # Safety
+///
+/// `store` must be null or a live store, and `hash` null or a pointer to 32 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn htree_store_push(
+    store: *mut HtreeStore,
+    hash: *const u8,
+) -> *mut HtreeProof {
+    match (store.as_mut(), read_hash(hash)) {
+        (Some(store), Some(hash)) => proof_handle(Some(store.0.push(hash, ()))),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// Write the root of the store to the 32 bytes of `root`.
+///
+/// # Safety
+///
+/// `store` must be null or a live store, and `root` null or a pointer to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn htree_store_root(store: *const HtreeStore, root: *mut u8) -> HtreeStatus {
+    let Some(store) = store.as_ref() else {
+        return HtreeStatus::NullPointer;
+    };
+    if root.is_null() {
+        return HtreeStatus::NullPointer;
+    }
+    if store.0.is_empty() {
+        return HtreeStatus::EmptyStore;
+    }
+    write_hash(store.0.root(), root);
+    HtreeStatus::Ok
+}
+
+/// The proof of the `nth` element, null if there is none. Free it with [htree_proof_free].
+///
+/// # Safety
+///
+/// `store` must be null or a live store.
+#[no_mangle]
+pub unsafe extern "C" fn htree_store_proof(
+    store: *const HtreeStore,
+    nth: usize,
+) -> *mut HtreeProof {
+    proof_handle(store.as_ref().and_then(|store| store.0.proof(nth)))
+}
+
+/// The proof of the `nth` element as it was when the store had `size` elements, null if there is
+/// none. Free it with [htree_proof_free].
+///
+/// # Safety
+///
+/// `store` must be null or a live store.
+#[no_mangle]
+pub unsafe extern "C" fn htree_store_proof_at(
+    store: *const HtreeStore,
+    nth: usize,
+    size: usize,
+) -> *mut HtreeProof {
+    proof_handle(store.as_ref().and_then(|store| store.0.proof_at(nth, size)))
+}
+
+/// Free a proof.
+///
+/// # Safety
+///
+/// `proof` must be null or a proof returned by this library, and not be used after.
+#[no_mangle]
+pub unsafe extern "C" fn htree_proof_free(proof: *mut HtreeProof) {
+    if !proof.is_null() {
+        drop(Box::from_raw(proof));
+    }
+}
+
+/// Decode a proof from the `len` bytes of its binary encoding, null if they are not one. Free it
+/// with [htree_proof_free].
+///
+/// # Safety
+///
+/// `bytes` must point to `len` bytes, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn htree_proof_from_bytes(bytes: *const u8, len: usize) -> *mut HtreeProof {
+    if bytes.is_null() {
+        return ptr::null_mut();
+    }
+    proof_handle(Proof::from_bytes(slice::from_raw_parts(bytes, len)).ok())
+}
+
+/// The length of the binary encoding of the proof, written to `out` if it fits in its
+/// `capacity` bytes. 0 if the proof is null.
+///
+/// # Safety
+///
+/// `proof` must be null or a live proof, and `out` null or a pointer to `capacity` writable
+/// bytes.
+#[no_mangle]
+pub unsafe extern "C" fn htree_proof_to_bytes(
+    proof: *const HtreeProof,
+    out: *mut u8,
+    capacity: usize,
+) -> usize {
+    let Some(proof) = proof.as_ref() else {
+        return 0;
+    };
+    let bytes = proof.0.to_bytes();
+    if !out.is_null() && bytes.len() <= capacity {
+        ptr::copy_nonoverlapping(bytes.as_ptr(), out, bytes.len());
+    }
+    bytes.len()
+}
+
+/// The index of the element of the proof, 0 if it's null.
+///
+/// # Safety
+///
+/// `proof` must be null or a live proof.
+#[no_mangle]
+pub unsafe extern "C" fn htree_proof_nth(proof: *const HtreeProof) -> usize {
+    proof.as_ref().map_or(0, |proof| proof.0.nth())
+}
+
+/// Check the element of hash `hash` is the `nth` of the store of `size` elements with the root
+/// `root`, whose elements are placed with `layout`.
+///
+/// # Safety
+///
+/// `proof` must be null or a live proof, `hash` and `root` null or pointers to 32 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn htree_proof_verify(
+    proof: *const HtreeProof,
+    hash: *const u8,
+    nth: usize,
+    size: usize,
+    root: *const u8,
+    layout: HtreeLayout,
+) -> HtreeStatus {
+    match (proof.as_ref(), read_hash(hash), read_hash(root)) {
+        (Some(proof), Some(hash), Some(root)) => {
+            match proof.0.verify(hash, nth, size, root, layout.into()) {
+                Ok(_) => HtreeStatus::Ok,
+                Err(error) => error.into(),
+            }
+        }
+        _ => HtreeStatus::NullPointer,
+    }
+}
@@ -0,0 +1,37 @@
+use std::path::Path;
+use std::process::Command;
+use std::{env, fs};
+
+#[test]
+// Compile `tests/htree_test.c` against the static library and run it.
+fn c_program() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // the library is built next to the test binaries.
+    let exe = env::current_exe().unwrap();
+    let lib = exe.with_file_name("libhtree_ffi.a");
+    assert!(lib.exists(), "{} is missing", lib.display());
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join("htree_test");
+    let _ = fs::remove_file(&out);
+
+    let mut cc = Command::new(env::var("CC").unwrap_or_else(|_| "cc".to_owned()));
+    cc.arg("-Wall")
+        .arg("-Werror")
+        .arg("-I")
+        .arg(dir.join("include"))
+        .arg(dir.join("tests/htree_test.c"))
+        .arg(&lib)
+        .arg("-o")
+        .arg(&out);
+    if cfg!(target_os = "linux") {
+        cc.args(["-lpthread", "-ldl", "-lm"]);
+    }
+    assert!(cc.status().unwrap().success(), "compilation failed");
+
+    let output = Command::new(&out).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
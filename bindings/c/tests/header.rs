@@ -0,0 +1,24 @@
+use std::path::Path;
+use std::{env, fs};
+
+#[test]
+// The committed header is the one cbindgen generates, `UPDATE_HEADER=1` rewrites it.
+fn header() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let config = cbindgen::Config::from_file(dir.join("cbindgen.toml")).unwrap();
+    let mut header = vec![];
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(dir.join("src/lib.rs"))
+        .generate()
+        .unwrap()
+        .write(&mut header);
+    let path = dir.join("include/htree.h");
+    if env::var_os("UPDATE_HEADER").is_some() {
+        fs::write(&path, &header).unwrap();
+    }
+    assert!(
+        fs::read(&path).unwrap() == header,
+        "include/htree.h is outdated, run the tests with UPDATE_HEADER=1"
+    );
+}
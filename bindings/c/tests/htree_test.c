@@ -0,0 +1,86 @@
+/* Exercise the C API: run by tests/c.rs. */
+#include <assert.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "htree.h"
+
+static void element_hash(int i, uint8_t hash[32]) {
+    char data[16];
+    int len = snprintf(data, sizeof(data), "element %d", i);
+    assert(htree_hash((const uint8_t *)data, (size_t)len, hash) == HTREE_STATUS_OK);
+}
+
+static void check_layout(HtreeLayout layout) {
+    HtreeStore *store = htree_store_new(layout);
+    uint8_t hash[32], root[32], other[32];
+
+    assert(htree_store_len(store) == 0);
+    assert(htree_store_root(store, root) == HTREE_STATUS_EMPTY_STORE);
+    for (int i = 0; i < 10; i++) {
+        element_hash(i, hash);
+        HtreeProof *proof = htree_store_push(store, hash);
+        assert(proof != NULL);
+        assert(htree_proof_nth(proof) == (size_t)i);
+        assert(htree_store_root(store, root) == HTREE_STATUS_OK);
+        assert(htree_proof_verify(proof, hash, i, i + 1, root, layout) == HTREE_STATUS_OK);
+        htree_proof_free(proof);
+    }
+    assert(htree_store_len(store) == 10);
+    assert(htree_store_root(store, root) == HTREE_STATUS_OK);
+
+    element_hash(3, hash);
+    HtreeProof *proof = htree_store_proof(store, 3);
+    assert(proof != NULL);
+    assert(htree_proof_verify(proof, hash, 3, 10, root, layout) == HTREE_STATUS_OK);
+    assert(htree_proof_verify(proof, hash, 4, 10, root, layout) == HTREE_STATUS_WRONG_INDEX);
+    assert(htree_proof_verify(proof, hash, 3, 9, root, layout) == HTREE_STATUS_WRONG_SIZE);
+    element_hash(4, other);
+    assert(htree_proof_verify(proof, other, 3, 10, root, layout) == HTREE_STATUS_ROOT_MISMATCH);
+    assert(htree_proof_verify(proof, NULL, 3, 10, root, layout) == HTREE_STATUS_NULL_POINTER);
+    /* the proof is checked in the expected layout, not in the one it claims. */
+    HtreeLayout swapped = layout == HTREE_LAYOUT_MMR ? HTREE_LAYOUT_RFC6962 : HTREE_LAYOUT_MMR;
+    assert(htree_proof_verify(proof, hash, 3, 10, root, swapped) == HTREE_STATUS_WRONG_LAYOUT);
+
+    /* the binary encoding round-trips. */
+    size_t len = htree_proof_to_bytes(proof, NULL, 0);
+    uint8_t bytes[1024];
+    assert(len > 0 && len <= sizeof(bytes));
+    assert(htree_proof_to_bytes(proof, bytes, sizeof(bytes)) == len);
+    HtreeProof *decoded = htree_proof_from_bytes(bytes, len);
+    assert(decoded != NULL);
+    assert(htree_proof_verify(decoded, hash, 3, 10, root, layout) == HTREE_STATUS_OK);
+    assert(htree_proof_from_bytes(bytes, 1) == NULL);
+    htree_proof_free(decoded);
+    htree_proof_free(proof);
+
+    assert(htree_store_proof(store, 10) == NULL);
+    HtreeProof *past = htree_store_proof_at(store, 3, 5);
+    assert(past != NULL);
+    htree_proof_free(past);
+    htree_store_free(store);
+}
+
+int main(void) {
+    check_layout(HTREE_LAYOUT_BIT_REVERSED);
+    check_layout(HTREE_LAYOUT_RFC6962);
+    check_layout(HTREE_LAYOUT_MMR);
+
+    /* a store and a proof may be null. */
+    assert(htree_store_len(NULL) == 0);
+    assert(htree_store_push(NULL, NULL) == NULL);
+    htree_store_free(NULL);
+    htree_proof_free(NULL);
+
+    /* the hash of an element is the BLAKE3 hash of its data. */
+    uint8_t hash[32];
+    assert(htree_hash(NULL, 0, hash) == HTREE_STATUS_OK);
+    static const uint8_t empty[32] = {
+        0xaf, 0x13, 0x49, 0xb9, 0xf5, 0xf9, 0xa1, 0xa6, 0xa0, 0x40, 0x4d, 0xea, 0x36, 0xdc, 0xc9, 0x49,
+        0x9b, 0xcb, 0x25, 0xc9, 0xad, 0xc1, 0x12, 0xb7, 0xcc, 0x9a, 0x93, 0xca, 0xe4, 0x1f, 0x32, 0x62,
+    };
+    assert(memcmp(hash, empty, 32) == 0);
+
+    puts("ok");
+    return 0;
+}
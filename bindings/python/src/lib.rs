@@ -0,0 +1,399 @@
+//! Python bindings of the stores and of the proof verification, imported as `htree`.
+//!
+//! Hashes are hex strings, as in the JSON of the server and of its stores. The elements of a
+//! [HMap] are strings, the file names of the stores of a server, so the `data/<root>.store` files
+//! it writes are read by [HMap::load]:
+//!
+//! ```python
+//! import htree
+//! store = htree.HMap.load("data/" + open("data/HEAD").read().strip() + ".store")
+//! proof = store.proof(3)
+//! hash = htree.hash(open("data/" + store.get_hash(3), "rb").read())
+//! proof.verify(hash, 3, len(store), store.root(), store.layout)
+//! ```
+use htree_challenge::tree;
+use pyo3::create_exception;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::fs;
+use std::path::PathBuf;
+
+create_exception!(
+    htree,
+    VerifyError,
+    PyValueError,
+    "A proof failed to verify, the message telling why."
+);
+
+// Parse a hash from its hex string.
+fn parse_hash(hex: &str) -> PyResult<blake3::Hash> {
+    blake3::Hash::from_hex(hex)
+        .map_err(|e| PyValueError::new_err(format!("invalid hash {:?}: {}", hex, e)))
+}
+
+fn hex(hash: blake3::Hash) -> String {
+    hash.to_hex().to_string()
+}
+
+// Parse a layout from its name in the JSON of the stores.
+fn parse_layout(name: &str) -> PyResult<tree::Layout> {
+    match name {
+        "bit_reversed" => Ok(tree::Layout::BitReversed),
+        "rfc6962" => Ok(tree::Layout::Rfc6962),
+        "mmr" => Ok(tree::Layout::Mmr),
+        _ => Err(PyValueError::new_err(format!(
+            "unknown layout {:?}, expected \"bit_reversed\", \"rfc6962\" or \"mmr\"",
+            name
+        ))),
+    }
+}
+
+fn layout_name(layout: tree::Layout) -> &'static str {
+    match layout {
+        tree::Layout::BitReversed => "bit_reversed",
+        tree::Layout::Rfc6962 => "rfc6962",
+        tree::Layout::Mmr => "mmr",
+    }
+}
+
+fn json_error(error: serde_json::Error) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+/// The BLAKE3 hash of `data`: the hash of an element to push.
+#[pyfunction]
+fn hash(data: &[u8]) -> String {
+    hex(blake3::hash(data))
+}
+
+/// The root of a store holding elements of these hashes with `layout`, without building it.
+/// `None` when there is no hash.
+#[pyfunction]
+fn root_of(layout: &str, hashes: Vec<String>) -> PyResult<Option<String>> {
+    let hashes = hashes
+        .iter()
+        .map(|h| parse_hash(h))
+        .collect::<PyResult<Vec<_>>>()?;
+    Ok(parse_layout(layout)?.root_of(&hashes).map(hex))
+}
+
+/// A store of strings, placed in a hash tree with one of the layouts `"bit_reversed"` (the
+/// default), `"rfc6962"` or `"mmr"`.
+#[pyclass(module = "htree")]
+pub struct HMap(tree::HMap<String>);
+
+#[pymethods]
+impl HMap {
+    #[new]
+    #[pyo3(signature = (layout = "bit_reversed"))]
+    fn new(layout: &str) -> PyResult<Self> {
+        Ok(HMap(tree::HMap::with_layout(parse_layout(layout)?)))
+    }
+
+    /// The store holding the `(hash, element)` pairs in order, built from the leaves up.
+    #[staticmethod]
+    fn from_leaves(layout: &str, leaves: Vec<(String, String)>) -> PyResult<Self> {
+        let leaves = leaves
+            .into_iter()
+            .map(|(hash, data)| Ok((parse_hash(&hash)?, data)))
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(HMap(tree::HMap::from_leaves(parse_layout(layout)?, leaves)))
+    }
+
+    /// Read a store from its JSON.
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        serde_json::from_str(json).map(HMap).map_err(json_error)
+    }
+
+    /// The JSON of the store, as the server writes it.
+    fn to_json(&self) -> String {
+        serde_json::to_string(&self.0).unwrap()
+    }
+
+    /// Read a store from a file, such as the `data/<root>.store` files of a server.
+    #[staticmethod]
+    fn load(path: PathBuf) -> PyResult<Self> {
+        serde_json::from_slice(&fs::read(path)?)
+            .map(HMap)
+            .map_err(json_error)
+    }
+
+    /// Write the store to a file, in the format of the server.
+    fn save(&self, path: PathBuf) -> PyResult<()> {
+        Ok(fs::write(path, serde_json::to_vec(&self.0).unwrap())?)
+    }
+
+    #[getter]
+    fn layout(&self) -> &'static str {
+        layout_name(self.0.layout())
+    }
+
+    /// The root of the tree. Raises `ValueError` when the store is empty.
+    fn root(&self) -> PyResult<String> {
+        if self.0.is_empty() {
+            return Err(PyValueError::new_err("the store is empty"));
+        }
+        Ok(hex(self.0.root()))
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("HMap(layout={:?}, len={})", self.layout(), self.0.len())
+    }
+
+    /// Push an element of hash `hash` and return its proof.
+    fn push(&mut self, hash: &str, data: String) -> PyResult<Proof> {
+        Ok(Proof(self.0.push(parse_hash(hash)?, data)))
+    }
+
+    /// Push an element hashed by the store, the hash of its UTF-8 bytes, and return its proof.
+    fn push_item(&mut self, data: String) -> Proof {
+        Proof(self.0.push_item(data))
+    }
+
+    /// Check the `nth` element still hashes to its leaf. `False` when there is no such element.
+    fn verify_item(&self, nth: usize) -> bool {
+        self.0.verify_item(nth)
+    }
+
+    /// The proof of the `nth` element, `None` if there is none.
+    fn proof(&self, nth: usize) -> Option<Proof> {
+        self.0.proof(nth).map(Proof)
+    }
+
+    /// The proof of the `nth` element as it was when the store had `size` elements.
+    fn proof_at(&self, nth: usize, size: usize) -> Option<Proof> {
+        self.0.proof_at(nth, size).map(Proof)
+    }
+
+    /// The proofs of the elements at `indices`, `None` for the ones beyond the store.
+    fn proofs(&self, indices: Vec<usize>) -> Vec<Option<Proof>> {
+        self.0
+            .proofs(&indices)
+            .into_iter()
+            .map(|proof| proof.map(Proof))
+            .collect()
+    }
+
+    /// The proof this store has grown from the version having `old_size` elements.
+    fn consistency(&self, old_size: usize) -> Option<Consistency> {
+        self.0.consistency(old_size).map(Consistency)
+    }
+
+    /// The `nth` element, `None` if there is none.
+    fn get(&self, nth: usize) -> Option<String> {
+        self.0.get(nth)
+    }
+
+    /// The hash of the `nth` element, `None` if there is none.
+    fn get_hash(&self, nth: usize) -> Option<String> {
+        self.0.get_hash(nth).map(hex)
+    }
+}
+
+/// A proof of an element of a store, as returned by the stores and served by `GET /<id>/proof`.
+#[pyclass(module = "htree", eq, frozen)]
+#[derive(PartialEq)]
+pub struct Proof(tree::Proof);
+
+#[pymethods]
+impl Proof {
+    /// Read a proof from its JSON.
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        serde_json::from_str(json).map(Proof).map_err(json_error)
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string(&self.0).unwrap()
+    }
+
+    /// Read a proof from its binary encoding (`?encoding=bytes`).
+    #[staticmethod]
+    fn from_bytes(bytes: &[u8]) -> PyResult<Self> {
+        tree::Proof::from_bytes(bytes)
+            .map(Proof)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    fn to_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.0.to_bytes())
+    }
+
+    /// Read a proof from the hex string of its binary encoding (`?encoding=hex`).
+    #[staticmethod]
+    fn from_hex(hex: &str) -> PyResult<Self> {
+        tree::Proof::from_hex(hex)
+            .map(Proof)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    fn to_hex(&self) -> String {
+        self.0.to_hex()
+    }
+
+    /// Read a proof from the base64 string of its binary encoding (`?encoding=base64`).
+    #[staticmethod]
+    fn from_base64(base64: &str) -> PyResult<Self> {
+        tree::Proof::from_base64(base64)
+            .map(Proof)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    fn to_base64(&self) -> String {
+        self.0.to_base64()
+    }
+
+    /// The index of the element.
+    #[getter]
+    fn nth(&self) -> usize {
+        self.0.nth()
+    }
+
+    /// The size of the store the proof was made for, `None` for older proofs.
+    #[getter]
+    fn size(&self) -> Option<usize> {
+        self.0.size()
+    }
+
+    #[getter]
+    fn layout(&self) -> &'static str {
+        layout_name(self.0.layout())
+    }
+
+    /// The root of the store before the element was pushed, `None` if it was empty.
+    fn hash(&self) -> Option<String> {
+        self.0.hash().map(hex)
+    }
+
+    /// The root obtained from the hash of the element and the proof, to compare with the
+    /// trusted root.
+    fn prove_on(&self, hash: &str) -> PyResult<String> {
+        Ok(hex(*self.0.prove_on(parse_hash(hash)?)))
+    }
+
+    /// Check the element of hash `hash` is the `nth` of the store of `size` elements with the
+    /// root `root`, whose elements are placed with `layout`. Raises `VerifyError` telling why
+    /// when it isn't.
+    fn verify(
+        &self,
+        hash: &str,
+        nth: usize,
+        size: usize,
+        root: &str,
+        layout: &str,
+    ) -> PyResult<()> {
+        self.0
+            .verify(
+                parse_hash(hash)?,
+                nth,
+                size,
+                parse_hash(root)?,
+                parse_layout(layout)?,
+            )
+            .map(|_| ())
+            .map_err(|e| VerifyError::new_err(e.to_string()))
+    }
+
+    /// Check this proof, returned by a push, shows the element of hash `hash` was pushed to the
+    /// store of `size` elements with the root `old_root` (`None` when empty), whose elements
+    /// are placed with `layout`. Returns the new root, raises `VerifyError` telling why it
+    /// failed.
+    #[pyo3(signature = (hash, size, old_root, layout))]
+    fn verify_append(
+        &self,
+        hash: &str,
+        size: usize,
+        old_root: Option<&str>,
+        layout: &str,
+    ) -> PyResult<String> {
+        let old_root = old_root.map(parse_hash).transpose()?;
+        self.0
+            .verify_append(parse_hash(hash)?, size, old_root, parse_layout(layout)?)
+            .map(|verified| hex(verified.root()))
+            .map_err(|e| VerifyError::new_err(e.to_string()))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Proof(nth={}, size={:?}, layout={:?})",
+            self.0.nth(),
+            self.0.size(),
+            self.layout()
+        )
+    }
+}
+
+/// A proof that a store is an extension of an older version of itself.
+#[pyclass(module = "htree", frozen)]
+pub struct Consistency(tree::Consistency);
+
+#[pymethods]
+impl Consistency {
+    /// Read a consistency proof from its JSON.
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        serde_json::from_str(json)
+            .map(Consistency)
+            .map_err(json_error)
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string(&self.0).unwrap()
+    }
+
+    /// The size of the old version of the store.
+    #[getter]
+    fn old_size(&self) -> usize {
+        self.0.from()
+    }
+
+    /// The size of the new version of the store.
+    #[getter]
+    fn new_size(&self) -> usize {
+        self.0.to()
+    }
+
+    /// The layout of the store the proof claims to be made for, to pin when none is trusted
+    /// yet.
+    #[getter]
+    fn layout(&self) -> &'static str {
+        layout_name(self.0.layout())
+    }
+
+    /// Check the store went from `old_root` (`None` when empty), the trusted root of its
+    /// version of `old_size` elements, to `new_root` only by pushing new elements, its elements
+    /// being placed with `layout`.
+    #[pyo3(signature = (old_size, old_root, new_root, layout))]
+    fn verify(
+        &self,
+        old_size: usize,
+        old_root: Option<&str>,
+        new_root: &str,
+        layout: &str,
+    ) -> PyResult<bool> {
+        let old_root = old_root.map(parse_hash).transpose()?;
+        Ok(self.0.verify(
+            old_size,
+            old_root,
+            parse_hash(new_root)?,
+            parse_layout(layout)?,
+        ))
+    }
+}
+
+#[pymodule]
+fn htree(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(hash, m)?)?;
+    m.add_function(wrap_pyfunction!(root_of, m)?)?;
+    m.add_class::<HMap>()?;
+    m.add_class::<Proof>()?;
+    m.add_class::<Consistency>()?;
+    m.add("VerifyError", m.py().get_type::<VerifyError>())?;
+    Ok(())
+}
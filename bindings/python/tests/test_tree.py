@@ -0,0 +1,303 @@
+"""The scenarios of `tests/tree.rs`, through the Python module.
+
+Run by `pytest` (or `python -m unittest`) once the module is installed by `maturin develop`.
+"""
+import json
+import os
+import re
+import tempfile
+import unittest
+
+import htree
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+LAYOUTS = ["bit_reversed", "rfc6962", "mmr"]
+
+
+def h(i):
+    return htree.hash(bytes([i]))
+
+
+def legacy(value):
+    """Replace the hex strings of hashes by arrays of bytes, as older versions wrote them."""
+    if isinstance(value, str):
+        try:
+            raw = bytes.fromhex(value)
+        except ValueError:
+            return value
+        return list(raw) if len(raw) == 32 else value
+    if isinstance(value, list):
+        return [legacy(v) for v in value]
+    if isinstance(value, dict):
+        return {k: legacy(v) for k, v in value.items()}
+    return value
+
+
+class TreeTest(unittest.TestCase):
+    def test_insert(self):
+        store = htree.HMap()
+        for i in range(6):
+            store.push(h(i), str(i))
+            self.assertEqual(store.get(i), str(i))
+        for i in range(6):
+            self.assertEqual(store.get(i), str(i))
+        self.assertEqual(len(store), 6)
+        self.assertIsNone(store.get(6))
+
+    def test_insert_proof(self):
+        store = htree.HMap()
+        for i in range(6):
+            self.assertEqual(store.push(h(i), str(i)), store.proof(i))
+
+    def test_proof(self):
+        store = htree.HMap()
+        root = None
+        for i in range(6):
+            store.push(h(i), str(i))
+            proof = store.proof(i)
+            self.assertEqual(proof.hash(), root)
+            root = proof.prove_on(h(i))
+        for i in range(6):
+            data = int(store.get(i))
+            self.assertEqual(store.proof(i).prove_on(h(data)), root)
+
+    def test_consistency(self):
+        store = htree.HMap()
+        roots = [None]
+        for i in range(20):
+            store.push(h(i), str(i))
+            roots.append(store.root())
+        root = store.root()
+        for old_size, old_root in enumerate(roots):
+            consistency = store.consistency(old_size)
+            self.assertEqual(consistency.old_size, old_size)
+            self.assertEqual(consistency.new_size, 20)
+            self.assertTrue(consistency.verify(old_size, old_root, root, "bit_reversed"))
+            if old_size > 0:
+                self.assertFalse(
+                    consistency.verify(old_size, roots[old_size - 1], root, "bit_reversed")
+                )
+        self.assertIsNone(store.consistency(21))
+
+    def test_consistency_expected(self):
+        # a consistency proof is checked from the trusted size, in the expected layout.
+        for layout in LAYOUTS:
+            store = htree.HMap(layout)
+            roots = [None]
+            for i in range(20):
+                store.push(h(i), str(i))
+                roots.append(store.root())
+            root = store.root()
+            for old_size, old_root in enumerate(roots):
+                consistency = store.consistency(old_size)
+                self.assertEqual(consistency.layout, layout)
+                self.assertTrue(consistency.verify(old_size, old_root, root, layout))
+                self.assertFalse(consistency.verify(old_size + 1, old_root, root, layout))
+                for other in LAYOUTS:
+                    if other != layout:
+                        self.assertFalse(consistency.verify(old_size, old_root, root, other))
+            with self.assertRaises(ValueError):
+                store.consistency(3).verify(3, roots[3], root, "unknown")
+
+    def test_root_of(self):
+        for layout in LAYOUTS:
+            store = htree.HMap(layout)
+            hashes = []
+            self.assertIsNone(htree.root_of(layout, hashes))
+            for i in range(20):
+                store.push(h(i), str(i))
+                hashes.append(h(i))
+                self.assertEqual(htree.root_of(layout, hashes), store.root())
+
+    def test_layouts(self):
+        # the scenarios of `rfc6962` and `mmr`.
+        for layout in ["rfc6962", "mmr"]:
+            store = htree.HMap(layout)
+            self.assertEqual(store.layout, layout)
+            roots = [None]
+            for i in range(20):
+                proof = store.push(h(i), str(i))
+                self.assertEqual(proof.layout, layout)
+                self.assertEqual(proof, store.proof(i))
+                self.assertEqual(proof.hash(), roots[-1])
+                self.assertEqual(proof.prove_on(h(i)), store.root())
+                self.assertEqual(
+                    proof.verify_append(h(i), i, roots[-1], layout), store.root()
+                )
+                roots.append(store.root())
+            root = store.root()
+            for i in range(20):
+                proof = store.proof(i)
+                self.assertEqual(store.get_hash(i), h(i))
+                self.assertEqual(proof.prove_on(h(i)), root)
+                self.assertNotEqual(proof.prove_on(h(i + 1)), root)
+            for old_size, old_root in enumerate(roots):
+                self.assertTrue(
+                    store.consistency(old_size).verify(old_size, old_root, root, layout)
+                )
+                if old_size > 1:
+                    self.assertFalse(
+                        store.consistency(old_size).verify(
+                            old_size, roots[old_size - 1], root, layout
+                        )
+                    )
+
+            # the store is saved and loaded back.
+            loaded = htree.HMap.from_json(store.to_json())
+            self.assertEqual(loaded.root(), root)
+            self.assertEqual(loaded.proof(7), store.proof(7))
+
+    def test_proof_at(self):
+        # every element is proven against every past root.
+        for layout in LAYOUTS:
+            store = htree.HMap(layout)
+            roots = []
+            for i in range(20):
+                store.push(h(i), str(i))
+                roots.append(store.root())
+            for size, root in zip(range(1, 21), roots):
+                for nth in range(size):
+                    store.proof_at(nth, size).verify(h(nth), nth, size, root, layout)
+                self.assertIsNone(store.proof_at(size, size))
+            self.assertIsNone(store.proof_at(3, 21))
+            self.assertEqual(store.proof_at(3, 20), store.proof(3))
+
+    def test_verify_errors(self):
+        store = htree.HMap("mmr")
+        for i in range(5):
+            store.push(h(i), str(i))
+        proof, root = store.proof(3), store.root()
+        proof.verify(h(3), 3, 5, root, "mmr")
+        for args in [
+            (h(4), 3, 5, root, "mmr"),
+            (h(3), 2, 5, root, "mmr"),
+            (h(3), 3, 4, root, "mmr"),
+        ]:
+            with self.assertRaises(htree.VerifyError):
+                proof.verify(*args)
+        with self.assertRaises(ValueError):
+            proof.verify("not a hash", 3, 5, root, "mmr")
+        # the proof is checked in the expected layout, not in the one it claims.
+        for other in ["bit_reversed", "rfc6962"]:
+            with self.assertRaises(htree.VerifyError):
+                proof.verify(h(3), 3, 5, root, other)
+            with self.assertRaises(htree.VerifyError):
+                store.proof(4).verify_append(h(4), 4, store.proof(4).hash(), other)
+        with self.assertRaises(ValueError):
+            proof.verify(h(3), 3, 5, root, "unknown")
+        with self.assertRaises(ValueError):
+            htree.HMap().root()
+        with self.assertRaises(ValueError):
+            htree.HMap("unknown")
+
+    def test_items(self):
+        # elements hashed by the store are checked back after a round trip through JSON.
+        store = htree.HMap("mmr")
+        for i in range(8):
+            proof = store.push_item("item {}".format(i))
+            self.assertEqual(
+                proof.prove_on(htree.hash("item {}".format(i).encode())), store.root()
+            )
+        data = json.loads(store.to_json())
+        loaded = htree.HMap.from_json(json.dumps(data))
+        self.assertTrue(all(loaded.verify_item(i) for i in range(8)))
+        data["data"][3] = "tampered"
+        tampered = htree.HMap.from_json(json.dumps(data))
+        self.assertFalse(tampered.verify_item(3))
+        self.assertTrue(tampered.verify_item(4))
+
+    def test_json(self):
+        for layout in LAYOUTS:
+            store = htree.HMap(layout)
+            for i in range(7):
+                store.push(h(i), str(i))
+            root = store.root()
+
+            data = json.loads(store.to_json())
+            self.assertEqual(htree.HMap.from_json(json.dumps(data)).root(), root)
+            self.assertEqual(htree.HMap.from_json(json.dumps(legacy(data))).root(), root)
+
+            proof = store.proof(5)
+            data = json.loads(proof.to_json())
+            self.assertTrue(all(isinstance(x, str) for x in data["hashes"]))
+            self.assertEqual(htree.Proof.from_json(json.dumps(data)), proof)
+            self.assertEqual(htree.Proof.from_json(json.dumps(legacy(data))), proof)
+            self.assertEqual(htree.Proof.from_bytes(proof.to_bytes()), proof)
+            self.assertEqual(htree.Proof.from_hex(proof.to_hex()), proof)
+            self.assertEqual(htree.Proof.from_base64(proof.to_base64()), proof)
+
+            consistency = store.consistency(2)
+            data = json.loads(consistency.to_json())
+            for loaded in [data, legacy(data)]:
+                loaded = htree.Consistency.from_json(json.dumps(loaded))
+                self.assertEqual(loaded.to_json(), consistency.to_json())
+        with self.assertRaises(ValueError):
+            htree.Proof.from_json("{}")
+        with self.assertRaises(ValueError):
+            htree.Proof.from_hex("zz")
+
+    def test_store_file(self):
+        # a store is read back from a file in the format of the `data/<root>.store` of a server.
+        for layout in LAYOUTS:
+            store = htree.HMap(layout)
+            for i in range(9):
+                store.push(htree.hash("file {}".format(i).encode()), "file{}".format(i))
+            with tempfile.TemporaryDirectory() as data:
+                path = os.path.join(data, store.root() + ".store")
+                store.save(path)
+                loaded = htree.HMap.load(path)
+            self.assertEqual(loaded.layout, layout)
+            self.assertEqual(len(loaded), 9)
+            self.assertEqual(loaded.root(), store.root())
+            self.assertEqual(loaded.get(4), "file4")
+            loaded.proof(4).verify(htree.hash(b"file 4"), 4, 9, store.root(), layout)
+        with self.assertRaises(OSError):
+            htree.HMap.load(os.path.join(data, "missing.store"))
+
+    def test_proofs(self):
+        for layout in LAYOUTS:
+            leaves = [(h(i), str(i)) for i in range(50)]
+            store = htree.HMap.from_leaves(layout, leaves)
+            indices = [0, 7, 49, 50, 7]
+            self.assertEqual(store.proofs(indices), [store.proof(nth) for nth in indices])
+
+    def test_from_leaves(self):
+        # a store built from its leaves is the one pushing them gives.
+        for layout in LAYOUTS:
+            for size in [0, 1, 2, 3, 7, 8, 9, 100]:
+                leaves = [(htree.hash(str(i).encode()), str(i)) for i in range(size)]
+                built = htree.HMap.from_leaves(layout, leaves)
+                pushed = htree.HMap(layout)
+                for hash, data in leaves:
+                    pushed.push(hash, data)
+                self.assertEqual(json.loads(built.to_json()), json.loads(pushed.to_json()))
+                if size > 0:
+                    self.assertEqual(built.root(), pushed.root())
+                    self.assertEqual(built.proof(size // 2), pushed.proof(size // 2))
+
+
+if __name__ == "__main__":
+    unittest.main()
+
+    def test_module_example(self):
+        # the example of the module documentation runs in the working directory of a server.
+        with open(os.path.join(HERE, "..", "src", "lib.rs")) as lib:
+            docs = "".join(line[4:] for line in lib if line.startswith("//! "))
+        example = re.search(r"```python\n(.*?)```", docs, re.S).group(1)
+        cwd = os.getcwd()
+        with tempfile.TemporaryDirectory() as server:
+            os.mkdir(os.path.join(server, "data"))
+            store = htree.HMap()
+            for i in range(5):
+                content = "file {}".format(i).encode()
+                store.push(htree.hash(content), "file{}".format(i))
+                with open(os.path.join(server, "data", htree.hash(content)), "wb") as file:
+                    file.write(content)
+            store.save(os.path.join(server, "data", store.root() + ".store"))
+            with open(os.path.join(server, "data", "HEAD"), "w") as head:
+                head.write(store.root())
+            os.chdir(server)
+            try:
+                exec(example, {})
+            finally:
+                os.chdir(cwd)
@@ -0,0 +1,201 @@
+//! WebAssembly bindings to verify the files of a `htree-server` in the browser.
+//!
+//! Hashes are hex strings, as in the JSON answers of the server. A download is checked by hashing
+//! it with [hash_blob] or [hash_stream] and verifying its proof on the hash, both fetched at the
+//! trusted root and size so the proof is the one of the trusted store:
+//!
+//! ```js
+//! const at = `root=${trustedRoot}&size=${trustedSize}`;
+//! const proof = Proof.fromJson(await (await fetch(`${server}/${nth}/proof?${at}`)).text());
+//! const hash = await hashBlob(await (await fetch(`${server}/${nth}?${at}`)).blob());
+//! // throws why the download is corrupted.
+//! proof.verify(hash, nth, trustedSize, trustedRoot, trustedLayout);
+//! ```
+//!
+//! The tests are run in node, on the `wasm32-unknown-unknown` target only (see
+//! `.cargo/config.toml`): `cargo test --workspace` builds none of them.
+use htree_challenge::tree;
+use js_sys::{Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, ReadableStream, ReadableStreamDefaultReader};
+
+/// Size of the slices of a [Blob] read at once by [hash_blob].
+const CHUNK: f64 = (1 << 20) as f64;
+
+// Parse a hash from its hex string.
+fn parse_hash(hex: &str) -> Result<blake3::Hash, JsError> {
+    blake3::Hash::from_hex(hex).map_err(|e| JsError::new(&format!("invalid hash {:?}: {}", hex, e)))
+}
+
+// Parse a layout from its name: `"bit_reversed"`, `"rfc6962"` or `"mmr"`.
+fn parse_layout(name: &str) -> Result<tree::Layout, JsError> {
+    match name {
+        "bit_reversed" => Ok(tree::Layout::BitReversed),
+        "rfc6962" => Ok(tree::Layout::Rfc6962),
+        "mmr" => Ok(tree::Layout::Mmr),
+        _ => Err(JsError::new(&format!("unknown layout {:?}", name))),
+    }
+}
+
+/// A proof of an element of a store, as served by `GET /<id>/proof`.
+#[wasm_bindgen]
+pub struct Proof(tree::Proof);
+
+#[wasm_bindgen]
+impl Proof {
+    /// Read a proof from its JSON.
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str) -> Result<Proof, JsError> {
+        Ok(Proof(serde_json::from_str(json)?))
+    }
+
+    /// Read a proof from its binary encoding (`?encoding=bytes`).
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Proof, JsError> {
+        Ok(Proof(tree::Proof::from_bytes(bytes)?))
+    }
+
+    /// Read a proof from the hex string of its binary encoding (`?encoding=hex`).
+    #[wasm_bindgen(js_name = fromHex)]
+    pub fn from_hex(hex: &str) -> Result<Proof, JsError> {
+        Ok(Proof(tree::Proof::from_hex(hex)?))
+    }
+
+    /// Read a proof from the base64 string of its binary encoding (`?encoding=base64`).
+    #[wasm_bindgen(js_name = fromBase64)]
+    pub fn from_base64(base64: &str) -> Result<Proof, JsError> {
+        Ok(Proof(tree::Proof::from_base64(base64)?))
+    }
+
+    /// The JSON of the proof.
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.0).unwrap()
+    }
+
+    /// The index of the element.
+    #[wasm_bindgen(getter)]
+    pub fn nth(&self) -> usize {
+        self.0.nth()
+    }
+
+    /// The size of the store the proof was made for, `undefined` for older proofs.
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> Option<usize> {
+        self.0.size()
+    }
+
+    /// The layout of the store: `"bit_reversed"`, `"rfc6962"` or `"mmr"`.
+    #[wasm_bindgen(getter)]
+    pub fn layout(&self) -> String {
+        match self.0.layout() {
+            tree::Layout::BitReversed => "bit_reversed",
+            tree::Layout::Rfc6962 => "rfc6962",
+            tree::Layout::Mmr => "mmr",
+        }
+        .to_owned()
+    }
+
+    /// Perform the proof over the hash of the data to check.
+    #[wasm_bindgen(js_name = proveOn)]
+    pub fn prove_on(&self, hash: &str) -> Result<PartialProof, JsError> {
+        Ok(PartialProof(self.0.prove_on(parse_hash(hash)?)))
+    }
+
+    /// Check the data of hash `hash` is the `nth` element of the store of `size` elements with
+    /// the root `root`, whose elements are placed with `layout` (see [Proof::layout]). Throws
+    /// the reason of a failure.
+    pub fn verify(
+        &self,
+        hash: &str,
+        nth: usize,
+        size: usize,
+        root: &str,
+        layout: &str,
+    ) -> Result<(), JsError> {
+        self.0
+            .verify(
+                parse_hash(hash)?,
+                nth,
+                size,
+                parse_hash(root)?,
+                parse_layout(layout)?,
+            )
+            .map(|_| ())
+            .map_err(JsError::from)
+    }
+}
+
+/// A proof performed over the hash of some data, returned by [Proof::prove_on].
+#[wasm_bindgen]
+pub struct PartialProof(tree::PartialProof);
+
+#[wasm_bindgen]
+impl PartialProof {
+    /// Check the root obtained is `root`, the trusted root of the store.
+    pub fn against(&self, root: &str) -> Result<bool, JsError> {
+        Ok(self.0.against(parse_hash(root)?))
+    }
+
+    /// The root obtained from the data and the proof.
+    #[wasm_bindgen(getter)]
+    pub fn root(&self) -> String {
+        self.0.to_hex().to_string()
+    }
+}
+
+/// Incremental BLAKE3 hashing, for data received in chunks.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct Hasher(blake3::Hasher);
+
+#[wasm_bindgen]
+impl Hasher {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Hasher {
+        Hasher::default()
+    }
+
+    /// Hash the next chunk of the data.
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.0.update(chunk);
+    }
+
+    /// The hash of the data so far.
+    pub fn digest(&self) -> String {
+        self.0.finalize().to_hex().to_string()
+    }
+}
+
+/// Hash a [Blob] (or a `File`), reading it by slices of 1 MiB.
+#[wasm_bindgen(js_name = hashBlob)]
+pub async fn hash_blob(blob: Blob) -> Result<String, JsValue> {
+    let mut hasher = Hasher::new();
+    let mut start = 0.0;
+    while start < blob.size() {
+        let end = (start + CHUNK).min(blob.size());
+        let slice = blob.slice_with_f64_and_f64(start, end)?;
+        let buffer = JsFuture::from(slice.array_buffer()).await?;
+        hasher.update(&Uint8Array::new(&buffer).to_vec());
+        start = end;
+    }
+    Ok(hasher.digest())
+}
+
+/// Hash the bytes of a `ReadableStream`, such as the body of a `fetch` response.
+#[wasm_bindgen(js_name = hashStream)]
+pub async fn hash_stream(stream: ReadableStream) -> Result<String, JsValue> {
+    let reader: ReadableStreamDefaultReader = stream.get_reader().unchecked_into();
+    let mut hasher = Hasher::new();
+    loop {
+        let chunk = JsFuture::from(reader.read()).await?;
+        if Reflect::get(&chunk, &"done".into())?.is_truthy() {
+            break;
+        }
+        let value: Uint8Array = Reflect::get(&chunk, &"value".into())?.dyn_into()?;
+        hasher.update(&value.to_vec());
+    }
+    Ok(hasher.digest())
+}
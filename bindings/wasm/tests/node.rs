@@ -0,0 +1,73 @@
+//! Run in node by `cargo test -p htree-wasm --target wasm32-unknown-unknown`.
+#![cfg(target_arch = "wasm32")]
+use htree_challenge::tree::{HMap, Layout};
+use htree_wasm::{hash_blob, hash_stream, Hasher, Proof};
+use js_sys::{Array, Uint8Array};
+use wasm_bindgen_test::wasm_bindgen_test;
+use web_sys::Blob;
+
+fn blob(parts: &[&[u8]]) -> Blob {
+    let parts: Array = parts.iter().map(|part| Uint8Array::from(*part)).collect();
+    Blob::new_with_u8_array_sequence(&parts).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn proofs() {
+    for layout in [Layout::BitReversed, Layout::Rfc6962, Layout::Mmr] {
+        let mut store = HMap::with_layout(layout);
+        for i in 0u8..5 {
+            store.push(blake3::hash(&[i]), i);
+        }
+        let root = store.root().to_hex().to_string();
+        let proof = store.proof(3).unwrap();
+        let hash = blake3::hash(&[3]).to_hex().to_string();
+        for proof in [
+            Proof::from_json(&serde_json::to_string(&proof).unwrap()).unwrap(),
+            Proof::from_bytes(&proof.to_bytes()).unwrap(),
+            Proof::from_hex(&proof.to_hex()).unwrap(),
+            Proof::from_base64(&proof.to_base64()).unwrap(),
+        ] {
+            assert_eq!((proof.nth(), proof.size()), (3, Some(5)));
+            let partial = proof.prove_on(&hash).unwrap();
+            assert!(partial.against(&root).unwrap());
+            assert_eq!(partial.root(), root);
+            let name = proof.layout();
+            assert!(proof.verify(&hash, 3, 5, &root, &name).is_ok());
+            assert!(proof.verify(&hash, 2, 5, &root, &name).is_err());
+            // the proof is checked in the expected layout, not in the one it claims.
+            for other in ["bit_reversed", "rfc6962", "mmr"] {
+                assert_eq!(
+                    proof.verify(&hash, 3, 5, &root, other).is_ok(),
+                    other == name
+                );
+            }
+            assert!(proof.verify(&hash, 3, 5, &root, "flat").is_err());
+            let other = blake3::hash(b"other").to_hex().to_string();
+            assert!(!proof.prove_on(&other).unwrap().against(&root).unwrap());
+        }
+    }
+    assert!(Proof::from_json("{}").is_err());
+    assert!(Proof::from_hex("zz").is_err());
+}
+
+#[wasm_bindgen_test]
+fn hasher() {
+    let mut hasher = Hasher::new();
+    hasher.update(b"hello ");
+    hasher.update(b"world");
+    assert_eq!(
+        hasher.digest(),
+        blake3::hash(b"hello world").to_hex().as_str()
+    );
+}
+
+#[wasm_bindgen_test]
+async fn blobs() {
+    let data: Vec<u8> = (0..3 << 20).map(|i| i as u8).collect();
+    let expected = blake3::hash(&data).to_hex().to_string();
+    let blob = blob(&[&data[..1000], &data[1000..]]);
+    assert_eq!(hash_blob(blob.clone()).await.unwrap(), expected);
+    assert_eq!(hash_stream(blob.stream()).await.unwrap(), expected);
+    let empty = blake3::hash(b"").to_hex().to_string();
+    assert_eq!(hash_blob(self::blob(&[])).await.unwrap(), empty);
+}
@@ -81,6 +81,20 @@
           inherit cargoArtifacts;
           cargoExtraArgs = "--bin htree-client";
         });
+
+        # The library without default features, built for a target without `std`.
+        noStdTarget = "thumbv7em-none-eabihf";
+        craneLibNoStd = crane.lib.${system}.overrideToolchain (rust.override {
+          targets = [ noStdTarget ];
+        });
+        htree-challenge-no-std = craneLibNoStd.mkCargoDerivation (commonArgs // {
+          # the dummy sources of the dependencies only build with `std`.
+          cargoArtifacts = null;
+          pnameSuffix = "-no-std";
+          buildPhaseCargoCommand = "cargo build --release --lib --no-default-features --target ${noStdTarget}";
+          doInstallCargoArtifacts = false;
+          installPhaseCommand = "mkdir -p $out";
+        });
       in
       {
         checks = {
@@ -98,6 +112,9 @@
             cargoClippyExtraArgs = "--all-targets -- --deny warnings";
           });
 
+          # Check the library builds without `std`.
+          inherit htree-challenge-no-std;
+
           htree-challenge-doc = craneLib.cargoDoc (commonArgs // {
             inherit cargoArtifacts;
           });
@@ -0,0 +1,28 @@
+//! Decode arbitrary input as a proof in every format: decoding never panics and a decoded proof
+//! is read back from its encodings.
+#![no_main]
+use htree_challenge::tree::{Consistency, Proof};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(proof) = Proof::from_bytes(data) {
+        assert_eq!(Proof::from_bytes(&proof.to_bytes()).unwrap(), proof);
+        assert_eq!(Proof::from_hex(&proof.to_hex()).unwrap(), proof);
+        assert_eq!(Proof::from_base64(&proof.to_base64()).unwrap(), proof);
+    }
+    if let Ok(text) = std::str::from_utf8(data) {
+        let _ = Proof::from_hex(text);
+        let _ = Proof::from_base64(text);
+    }
+    if let Ok(proof) = serde_json::from_slice::<Proof>(data) {
+        let json = serde_json::to_string(&proof).unwrap();
+        assert_eq!(serde_json::from_str::<Proof>(&json).unwrap(), proof);
+    }
+    if let Ok(consistency) = serde_json::from_slice::<Consistency>(data) {
+        let json = serde_json::to_string(&consistency).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Consistency>(&json).unwrap(),
+            consistency
+        );
+    }
+});
@@ -0,0 +1,60 @@
+//! Verify arbitrary proofs of arbitrary elements against a store: verifying never panics, and a
+//! proof which verifies is about the element of the store at its index.
+#![no_main]
+use arbitrary::Arbitrary;
+use htree_challenge::tree::{HMap, Layout, Proof};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    layout: u8,
+    /// The store has `size + 1` elements.
+    size: u8,
+    /// The index to verify, beyond the store too.
+    nth: u16,
+    /// The hash of the element to verify, the one of the store at `nth` when `None`.
+    hash: Option<[u8; 32]>,
+    /// The encoding of the proof, the one of the honest proof of `nth` xored by `edits` when
+    /// `None`.
+    proof: Option<Vec<u8>>,
+    edits: Vec<(u16, u8)>,
+}
+
+fuzz_target!(|input: Input| {
+    let layout = [Layout::BitReversed, Layout::Rfc6962, Layout::Mmr][input.layout as usize % 3];
+    let size = input.size as usize + 1;
+    let hashes: Vec<_> = (0..size).map(|i| blake3::hash(&i.to_le_bytes())).collect();
+    let store = HMap::from_leaves(layout, hashes.iter().map(|h| (*h, ())));
+    let root = store.root();
+    let nth = input.nth as usize % (size + 2);
+    let hash = input
+        .hash
+        .map_or(hashes[nth.min(size - 1)], blake3::Hash::from_bytes);
+    let bytes = input.proof.unwrap_or_else(|| {
+        let mut bytes = store.proof(nth.min(size - 1)).unwrap().to_bytes();
+        let len = bytes.len();
+        for (at, xor) in input.edits {
+            bytes[at as usize % len] ^= xor;
+        }
+        bytes
+    });
+    let Ok(proof) = Proof::from_bytes(&bytes) else {
+        return;
+    };
+
+    let _ = proof.hash();
+    let _ = proof.prove_on(hash).against(root);
+    if let Some(claimed) = proof.size() {
+        let _ = proof.verify(hash, proof.nth(), claimed, root, proof.layout());
+    }
+    if proof.verify(hash, nth, size, root, layout).is_ok() {
+        assert_eq!(hashes.get(nth), Some(&hash));
+    }
+    // as the push of the last element.
+    let old_root = layout.root_of(&hashes[..size - 1]);
+    if let Ok(verified) = proof.verify_append(hash, size - 1, old_root, layout) {
+        if verified.root() == root {
+            assert_eq!(hash, hashes[size - 1]);
+        }
+    }
+});
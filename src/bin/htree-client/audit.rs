@@ -0,0 +1,128 @@
+//! Audit of a whole store: every file is downloaded and proved against the trusted root, then
+//! the root is recomputed from the downloaded files.
+use htree_challenge::tree::{Layout, Proof, VerifyError};
+use reqwest::blocking::Client;
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Issue {
+    /// The file or its proof can't be downloaded.
+    Missing { nth: usize, error: String },
+    /// The downloaded file doesn't prove against the trusted root.
+    BadProof {
+        nth: usize,
+        hash: String,
+        reason: String,
+    },
+    /// The root recomputed from the downloaded files is not the trusted one.
+    RootMismatch { computed: String },
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub server: String,
+    pub root: String,
+    pub size: usize,
+    pub issues: Vec<Issue>,
+}
+
+/// Audit the `size` elements of the store of `base` (the server URL), whose elements are placed
+/// with `layout`, with `jobs` threads.
+pub fn audit(
+    client: &Client,
+    base: &str,
+    root: blake3::Hash,
+    size: usize,
+    layout: Layout,
+    jobs: usize,
+) -> Report {
+    let jobs = jobs.max(1);
+    let chunk = ((size + jobs - 1) / jobs).max(1);
+    let results: Vec<Checked> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..size)
+            .step_by(chunk)
+            .map(|start| {
+                scope.spawn(move || {
+                    (start..size.min(start + chunk))
+                        .map(|nth| check(client, base, root, nth, size, layout))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    });
+
+    let mut issues = Vec::new();
+    let mut hashes = Vec::new();
+    for (nth, result) in results.into_iter().enumerate() {
+        match result {
+            Ok((hash, error)) => {
+                if let Some(error) = error {
+                    issues.push(Issue::BadProof {
+                        nth,
+                        hash: hash.to_hex().to_string(),
+                        reason: error.to_string(),
+                    });
+                }
+                hashes.push(hash);
+            }
+            Err(issue) => issues.push(issue),
+        }
+    }
+    // the root can only be recomputed when every file was downloaded.
+    if hashes.len() == size {
+        if let Some(computed) = layout.root_of(&hashes).filter(|computed| *computed != root) {
+            issues.push(Issue::RootMismatch {
+                computed: computed.to_hex().to_string(),
+            });
+        }
+    }
+    Report {
+        server: base.to_owned(),
+        root: root.to_hex().to_string(),
+        size,
+        issues,
+    }
+}
+
+type Checked = Result<(blake3::Hash, Option<VerifyError>), Issue>;
+
+/// Download the `nth` file and its proof. Returns the hash of the file, and why it's not proved
+/// in `layout` if so.
+fn check(
+    client: &Client,
+    base: &str,
+    root: blake3::Hash,
+    nth: usize,
+    size: usize,
+    layout: Layout,
+) -> Checked {
+    let root_hex = root.to_hex().to_string();
+    let missing = |e: reqwest::Error| Issue::Missing {
+        nth,
+        error: e.to_string(),
+    };
+    let bytes = client
+        .get(format!("{}/{}", base, nth))
+        .query(&[("root", &root_hex)])
+        .query(&[("size", size)])
+        .send()
+        .and_then(|res| res.error_for_status())
+        .and_then(|res| res.bytes())
+        .map_err(missing)?;
+    let proof: Proof = client
+        .get(format!("{}/{}/proof", base, nth))
+        .query(&[("root", &root_hex)])
+        .query(&[("size", size)])
+        .send()
+        .and_then(|res| res.error_for_status())
+        .and_then(|res| res.json())
+        .map_err(missing)?;
+    let hash = blake3::hash(&bytes);
+    let error = proof.verify(hash, nth, size, root, layout).err();
+    Ok((hash, error))
+}
@@ -0,0 +1,393 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use htree_challenge::tree::{Consistency, Proof};
+use reqwest::blocking::{multipart::*, Client, Response};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::{Certificate, Identity};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+mod audit;
+mod state;
+use state::State;
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create the empty store of the server, before the first push.
+    Init,
+    Get {
+        nth: usize,
+        file: String,
+    },
+    Proof {
+        nth: usize,
+        file: String,
+    },
+    Push {
+        file: String,
+    },
+    /// Fetch the latest root of the server and check it extends the trusted one.
+    Sync,
+    /// Print what is known of the server.
+    Status,
+    /// Print the roots of the server verified so far.
+    History {
+        /// Compare them with the log of the roots of the server (`GET /roots`).
+        #[arg(long)]
+        check: bool,
+    },
+    /// Download and prove every file of the server against the trusted root.
+    Audit {
+        /// Print the report as JSON.
+        #[arg(long)]
+        json: bool,
+        /// Number of parallel downloads. Defaults to the number of CPUs.
+        #[arg(long)]
+        jobs: Option<usize>,
+    },
+}
+
+/// Response of `GET /head`.
+#[derive(Deserialize)]
+struct Head {
+    size: usize,
+    root: Option<String>,
+    consistency: Option<Consistency>,
+}
+
+/// A page of `GET /roots`.
+#[derive(Deserialize)]
+struct Roots {
+    total: usize,
+    roots: Vec<LoggedRoot>,
+}
+
+#[derive(Deserialize)]
+struct LoggedRoot {
+    size: usize,
+    root: String,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Scheme {
+    Http,
+    Https,
+}
+
+#[derive(Parser)]
+struct ClientArgs {
+    #[command(subcommand)]
+    cmd: Command,
+    server: String,
+    #[arg(default_value_t = 2636)]
+    port: u16,
+    #[arg(long, value_enum, default_value_t = Scheme::Http)]
+    scheme: Scheme,
+    /// PEM bundle of extra CAs to trust for HTTPS (e.g. a self-signed server certificate).
+    #[arg(long)]
+    ca_cert: Option<PathBuf>,
+    /// PEM file with the client certificate and its private key, for servers requiring mTLS.
+    #[arg(long)]
+    identity: Option<PathBuf>,
+    /// Tenant to use on servers hosting several stores.
+    #[arg(long, env = "HTREE_TENANT")]
+    tenant: Option<String>,
+    /// Bearer token for servers requiring authentication.
+    #[arg(long, env = "HTREE_TOKEN")]
+    token: Option<String>,
+    /// File holding the trusted roots. Defaults to `$XDG_STATE_HOME/htree/roots.json`.
+    #[arg(long, env = "HTREE_STATE")]
+    state: Option<PathBuf>,
+}
+
+impl ClientArgs {
+    fn url(&self, path: &str) -> String {
+        let scheme = match self.scheme {
+            Scheme::Http => "http",
+            Scheme::Https => "https",
+        };
+        format!("{}://{}:{}{}", scheme, self.server, self.port, path)
+    }
+
+    /// Key of the server in the state file: its URL, and its tenant if any.
+    fn state_key(&self) -> String {
+        match &self.tenant {
+            Some(tenant) => format!("{} {}", self.url(""), tenant),
+            None => self.url(""),
+        }
+    }
+
+    fn client(&self) -> Client {
+        let mut builder = Client::builder().use_rustls_tls();
+        if let Some(path) = &self.ca_cert {
+            for cert in Certificate::from_pem_bundle(&fs::read(path).unwrap()).unwrap() {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        if let Some(path) = &self.identity {
+            builder = builder.identity(Identity::from_pem(&fs::read(path).unwrap()).unwrap());
+        }
+        let mut headers = HeaderMap::new();
+        if let Some(tenant) = &self.tenant {
+            headers.insert("x-htree-tenant", HeaderValue::from_str(tenant).unwrap());
+        }
+        if let Some(token) = &self.token {
+            let mut value = HeaderValue::from_str(&format!("Bearer {}", token)).unwrap();
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+        builder = builder.default_headers(headers);
+        builder.build().unwrap()
+    }
+}
+
+/// Stop with the error the server explains in the body of `res`, if any.
+fn check_status(res: Response) -> Response {
+    let status = res.status();
+    if status.is_client_error() || status.is_server_error() {
+        panic!(
+            "Server error {}: {}",
+            status,
+            res.text().unwrap_or_default()
+        );
+    }
+    res
+}
+
+/// Fetch every page of `GET /roots`: the root the server logged for each size.
+fn logged_roots(client: &Client, args: &ClientArgs) -> HashMap<usize, String> {
+    let mut logged = HashMap::new();
+    let mut offset = 0;
+    loop {
+        let page: Roots = check_status(
+            client
+                .get(args.url("/roots"))
+                .query(&[("offset", offset)])
+                .send()
+                .unwrap(),
+        )
+        .json()
+        .unwrap();
+        offset += page.roots.len();
+        let last = page.roots.is_empty() || offset >= page.total;
+        logged.extend(page.roots.into_iter().map(|r| (r.size, r.root)));
+        if last {
+            return logged;
+        }
+    }
+}
+
+fn main() {
+    let args = ClientArgs::parse();
+    let mut state = State::open(&args.state.clone().unwrap_or_else(State::default_path)).unwrap();
+    let key = args.state_key();
+    if matches!(args.scheme, Scheme::Http) && args.tenant.is_none() {
+        // clients older than the state file kept the roots by host in the working directory.
+        state.import_legacy(Path::new("roots.json"), &args.server, &key);
+    }
+    let client = args.client();
+    match &args.cmd {
+        Command::Init => {
+            check_status(client.post(args.url("/init")).send().unwrap());
+            println!("Initialized an empty store");
+        }
+        Command::Push { file } => {
+            let bytes = fs::read(file.clone()).unwrap();
+            let hash = blake3::hash(&bytes);
+            let req = client.post(args.url("")).multipart(
+                Form::new()
+                    .text("hash", hash.to_hex().to_string())
+                    .file("file", file)
+                    .unwrap(),
+            );
+            let known = state.server(&key).cloned();
+            let res = if let Some(known) = &known {
+                req.query(&[("root", &known.root)])
+            } else {
+                req
+            }
+            .send();
+            let proof: Proof = check_status(res.unwrap()).json().unwrap();
+            // the roots imported from older clients don't know the size.
+            let size = known
+                .as_ref()
+                .map_or(Some(0), |k| k.size)
+                .unwrap_or(proof.nth());
+            // the layout of a new server is the one of its first proof.
+            let layout = known.as_ref().map_or(proof.layout(), |k| k.layout);
+            let verified = proof
+                .verify_append(hash, size, known.map(|k| k.root()), layout)
+                .unwrap_or_else(|e| panic!("Server corupted: {}", e));
+            println!("Uploaded ID: {}", verified.nth());
+            state.set_root(&key, verified.root(), verified.size(), layout);
+            state.save().unwrap();
+        }
+        Command::Get { nth, file } => {
+            let known = state.server(&key).expect("No known root for this server");
+            let size = known
+                .size
+                .expect("Unknown size for the trusted root, sync to update it");
+            let root = &known.root;
+            let res = client
+                .get(args.url(&format!("/{}", nth)))
+                .query(&[("root", root)])
+                .query(&[("size", size)])
+                .send()
+                .unwrap();
+            let bytes = check_status(res).bytes().unwrap();
+            let res = client
+                .get(args.url(&format!("/{}/proof", nth)))
+                .query(&[("root", root)])
+                .query(&[("size", size)])
+                .send()
+                .unwrap();
+            let proof: Proof = check_status(res).json().unwrap();
+            match proof.verify(blake3::hash(&bytes), *nth, size, known.root(), known.layout) {
+                Ok(_) => {
+                    fs::write(file.clone(), bytes).unwrap();
+                    println!("Downloaded file into: {}", file);
+                }
+                Err(e) => panic!("Server corupted: {}", e),
+            }
+        }
+        Command::Proof { nth, file } => {
+            let known = state.server(&key).expect("No known root for this server");
+            let size = known
+                .size
+                .expect("Unknown size for the trusted root, sync to update it");
+            let root = &known.root;
+            let bytes = fs::read(file.clone()).unwrap();
+            let res = client
+                .get(args.url(&format!("/{}/proof", nth)))
+                .query(&[("root", root)])
+                .query(&[("size", size)])
+                .send()
+                .unwrap();
+            let proof: Proof = check_status(res).json().unwrap();
+            match proof.verify(blake3::hash(&bytes), *nth, size, known.root(), known.layout) {
+                Ok(_) => println!("Proved: {}", file),
+                Err(e) => panic!(
+                    "Proof failed: {}! you may try to provethe wrong file or may the server be corupted.",
+                    e
+                ),
+            }
+        }
+        Command::Sync => {
+            let known = state.server(&key);
+            let from = match known {
+                Some(known) => known
+                    .size
+                    .expect("Unknown size for the trusted root, push a file to update it"),
+                None => 0,
+            };
+            let head: Head = check_status(
+                client
+                    .get(args.url("/head"))
+                    .query(&[("from", from)])
+                    .send()
+                    .unwrap(),
+            )
+            .json()
+            .unwrap();
+            let Some(root) = head.root else {
+                if known.is_some() {
+                    panic!("Server corupted");
+                }
+                println!("Empty store");
+                return;
+            };
+            let root = blake3::Hash::from_hex(root).unwrap();
+            let consistency = head.consistency.expect("Server corupted");
+            let layout = known.map_or(consistency.layout(), |k| k.layout);
+            if consistency.to() == head.size
+                && consistency.verify(from, known.map(|k| k.root()), root, layout)
+            {
+                state.set_root(&key, root, head.size, layout);
+                state.save().unwrap();
+                println!("Synced: {} elements, root {}", head.size, root);
+            } else {
+                panic!("Server corupted");
+            }
+        }
+        Command::Status => {
+            println!("Server: {}", key);
+            if let Some(known) = state.server(&key) {
+                match known.size {
+                    Some(size) => println!("Size: {}", size),
+                    None => println!("Size: unknown"),
+                }
+                println!("Root: {}", known.root);
+                match known.synced {
+                    Some(synced) => println!(
+                        "Last sync: {}",
+                        humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_secs(synced))
+                    ),
+                    None => println!("Last sync: unknown"),
+                }
+            } else {
+                println!("No known root");
+            }
+        }
+        Command::History { check } => {
+            let history = state.server(&key).map_or(&[][..], |known| &known.history);
+            let logged = if *check {
+                logged_roots(&client, &args)
+            } else {
+                HashMap::new()
+            };
+            let mut disputed = false;
+            for checkpoint in history {
+                let verified = humantime::format_rfc3339_seconds(
+                    UNIX_EPOCH + Duration::from_secs(checkpoint.verified),
+                );
+                let server = match logged.get(&checkpoint.size) {
+                    _ if !check => String::new(),
+                    Some(root) if *root == checkpoint.root => {
+                        String::from(", logged by the server")
+                    }
+                    Some(root) => {
+                        disputed = true;
+                        format!(", DISPUTED: the server logged {}", root)
+                    }
+                    None => String::from(", not logged by the server"),
+                };
+                println!(
+                    "{} elements, root {}, verified {}{}",
+                    checkpoint.size, checkpoint.root, verified, server
+                );
+            }
+            if disputed {
+                std::process::exit(1);
+            }
+        }
+        Command::Audit { json, jobs } => {
+            let known = state.server(&key).expect("No known root for this server");
+            let size = known
+                .size
+                .expect("Unknown size for the trusted root, sync to update it");
+            let jobs =
+                jobs.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+            let report = audit::audit(
+                &client,
+                &args.url(""),
+                known.root(),
+                size,
+                known.layout,
+                jobs,
+            );
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&report).unwrap());
+            } else {
+                println!("{}: {} elements, root {}", key, report.size, report.root);
+                for issue in &report.issues {
+                    println!("  {:?}", issue);
+                }
+            }
+            if !report.issues.is_empty() {
+                std::process::exit(1);
+            }
+        }
+    };
+}
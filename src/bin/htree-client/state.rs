@@ -0,0 +1,167 @@
+//! Persistent client state: what is known of each server.
+//!
+//! The state is a JSON file, by default `$XDG_STATE_HOME/htree/roots.json`. The `.lock` file next
+//! to it is locked for as long as a [State] is alive, so concurrent invocations of the client run
+//! one after the other instead of forking the trusted root of a server. The state file itself is
+//! only ever replaced as a whole, so a crash can't leave it truncated.
+use fs2::FileExt;
+use htree_challenge::tree::Layout;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct State {
+    path: PathBuf,
+    _lock: File,
+    servers: HashMap<String, Server>,
+}
+
+/// What is known of a server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Server {
+    /// The trusted root, as a hex string.
+    pub root: String,
+    /// The size of the store at this root. Unknown for the roots imported from older clients.
+    pub size: Option<usize>,
+    /// Unix time of the last verified update of the root.
+    pub synced: Option<u64>,
+    /// The layout of the store, pinned at the first verification: the proofs of the server are
+    /// checked in it, whatever layout they claim. Older clients only knew the bit reversed one.
+    #[serde(default)]
+    pub layout: Layout,
+    /// The roots verified so far, oldest first. Older clients didn't keep them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<Checkpoint>,
+}
+
+/// A root of a server verified by the client.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub size: usize,
+    /// The root, as a hex string.
+    pub root: String,
+    /// Unix time of the verification.
+    pub verified: u64,
+}
+
+impl Server {
+    pub fn root(&self) -> blake3::Hash {
+        blake3::Hash::from_hex(&self.root).unwrap()
+    }
+}
+
+impl State {
+    /// Default location of the state file.
+    pub fn default_path() -> PathBuf {
+        dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("htree")
+            .join("roots.json")
+    }
+
+    /// Lock and read the state file, starting empty if it doesn't exist. Blocks while another
+    /// client holds the lock.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let lock = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(with_suffix(path, ".lock"))?;
+        lock.lock_exclusive()?;
+        let servers = match fs::read(path) {
+            Ok(json) if json.is_empty() => HashMap::new(),
+            Ok(json) => serde_json::from_slice(&json)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            path: path.to_owned(),
+            _lock: lock,
+            servers,
+        })
+    }
+
+    /// Adopt the root kept for `host` by the clients older than the state file, in the
+    /// `roots.json` of their working directory, if the server of `key` isn't known yet.
+    pub fn import_legacy(&mut self, legacy: &Path, host: &str, key: &str) {
+        if self.servers.contains_key(key) || is_same_file(legacy, &self.path) {
+            return;
+        }
+        // anything else than the map of roots by host of these clients is left alone.
+        let roots = fs::read(legacy)
+            .ok()
+            .and_then(|json| serde_json::from_slice::<HashMap<String, String>>(&json).ok());
+        if let Some(root) = roots.and_then(|mut roots| roots.remove(host)) {
+            self.servers.insert(
+                key.to_owned(),
+                Server {
+                    root,
+                    size: None,
+                    synced: None,
+                    layout: Layout::BitReversed,
+                    history: vec![],
+                },
+            );
+        }
+    }
+
+    pub fn server(&self, key: &str) -> Option<&Server> {
+        self.servers.get(key)
+    }
+
+    /// Record a verified root of a server of the given layout, and add it to its history.
+    pub fn set_root(&mut self, key: &str, root: blake3::Hash, size: usize, layout: Layout) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let root = root.to_hex().to_string();
+        let mut history = self
+            .servers
+            .remove(key)
+            .map_or_else(Vec::new, |server| server.history);
+        if history.last().map(|last| &last.root) != Some(&root) {
+            history.push(Checkpoint {
+                size,
+                root: root.clone(),
+                verified: now.as_secs(),
+            });
+        }
+        self.servers.insert(
+            key.to_owned(),
+            Server {
+                root,
+                size: Some(size),
+                synced: Some(now.as_secs()),
+                layout,
+                history,
+            },
+        );
+    }
+
+    /// Write the state back to its file: a temporary file is written and synced next to it,
+    /// then renamed over it.
+    pub fn save(&mut self) -> io::Result<()> {
+        let json = serde_json::to_vec(&self.servers)?;
+        let tmp = with_suffix(&self.path, ".tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(&json)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = OsString::from(path);
+    path.push(suffix);
+    path.into()
+}
+
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    matches!((a.canonicalize(), b.canonicalize()), (Ok(a), Ok(b)) if a == b)
+}
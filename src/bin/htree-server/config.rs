@@ -0,0 +1,336 @@
+//! Server configuration.
+//!
+//! The configuration is read from a TOML file (see `--config`), then overridden by the command
+//! line and the environment, and finally validated by [Config::validate] before the server
+//! starts. Every field is optional in the file; the defaults are documented on each field.
+//!
+//! ```toml
+//! data_dir = "data"
+//! bind = ["127.0.0.1:2636"]
+//! max_upload_size = 67108864
+//! layout = "bit_reversed"
+//!
+//! [tls]
+//! cert = "server.pem"
+//! key = "server.key"
+//! client_ca = "ca.pem"
+//!
+//! [auth]
+//! tokens = ["admin-secret"]
+//!
+//! [log]
+//! level = "info"
+//!
+//! [[tenants]]
+//! name = "alice"
+//! tokens = ["alice-secret"]
+//! layout = "rfc6962"
+//! ```
+use htree_challenge::tree::Layout;
+use serde::Deserialize;
+use std::fmt;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+
+/// Port of the default bind address.
+pub const DEFAULT_PORT: u16 = 2636;
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Directory holding the stores and the uploaded files. Default: `data`.
+    pub data_dir: PathBuf,
+    /// Addresses to listen on, as `host:port`. Default: `["127.0.0.1:2636"]`.
+    pub bind: Vec<String>,
+    /// Maximum size in bytes of an upload request. Default: 64 MiB.
+    pub max_upload_size: u64,
+    /// Layout of the new stores, `bit_reversed`, `rfc6962` or `mmr`. Default: `bit_reversed`.
+    pub layout: Layout,
+    /// Serve HTTPS instead of HTTP. Default: none.
+    pub tls: Option<Tls>,
+    pub auth: Auth,
+    pub log: Log,
+    /// Isolated stores. When empty, the server has a single store in `data_dir`. Otherwise each
+    /// tenant has its own store in `data_dir/<name>` and requests must name their tenant with
+    /// the `x-htree-tenant` header.
+    pub tenants: Vec<Tenant>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tls {
+    /// PEM certificate chain.
+    pub cert: PathBuf,
+    /// PEM private key of `cert`.
+    pub key: PathBuf,
+    /// PEM bundle of CAs allowed to sign client certificates. When set, clients must
+    /// authenticate with a certificate (mTLS).
+    pub client_ca: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Auth {
+    /// Bearer tokens granting access to every tenant. Default: none.
+    ///
+    /// When no token at all is configured (here or in the tenants), authentication is disabled.
+    pub tokens: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Log {
+    /// One of `error`, `warn`, `info`, `debug` or `trace`. Default: `info`.
+    pub level: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tenant {
+    /// Name of the tenant, also its directory in `data_dir`.
+    pub name: String,
+    /// Bearer tokens granting access to this tenant only. Default: none.
+    #[serde(default)]
+    pub tokens: Vec<String>,
+    /// Layout of the new stores of this tenant. Default: the global `layout`.
+    pub layout: Option<Layout>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::from("data"),
+            bind: vec![format!("127.0.0.1:{}", DEFAULT_PORT)],
+            max_upload_size: 64 * 1024 * 1024,
+            layout: Layout::default(),
+            tls: None,
+            auth: Auth::default(),
+            log: Log::default(),
+            tenants: vec![],
+        }
+    }
+}
+
+impl Default for Log {
+    fn default() -> Self {
+        Self {
+            level: String::from("info"),
+        }
+    }
+}
+
+/// Why a configuration can't be used.
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    NoBind,
+    Bind(String),
+    MaxUploadSize,
+    LogLevel(String),
+    Tls(PathBuf),
+    TenantName(String),
+    DuplicateTenant(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read(path, e) => write!(f, "can't read config file {}: {}", path.display(), e),
+            Self::Parse(path, e) => write!(f, "invalid config file {}: {}", path.display(), e),
+            Self::NoBind => write!(f, "`bind` must contain at least one address"),
+            Self::Bind(addr) => write!(f, "invalid bind address `{}`, expected `host:port`", addr),
+            Self::MaxUploadSize => write!(f, "`max_upload_size` must be greater than 0"),
+            Self::LogLevel(level) => write!(
+                f,
+                "invalid log level `{}`, expected one of error, warn, info, debug, trace",
+                level
+            ),
+            Self::Tls(path) => write!(f, "TLS file {} does not exist", path.display()),
+            Self::TenantName(name) => write!(
+                f,
+                "invalid tenant name `{}`, only ASCII letters, digits, `-` and `_` are allowed",
+                name
+            ),
+            Self::DuplicateTenant(name) => write!(f, "tenant `{}` is defined twice", name),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Read the config file at `path`.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| ConfigError::Read(path.to_owned(), e))?;
+        toml::from_str(&content).map_err(|e| ConfigError::Parse(path.to_owned(), e))
+    }
+
+    /// Check the config is usable, so the server fails at startup rather than on the first
+    /// request.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.bind.is_empty() {
+            return Err(ConfigError::NoBind);
+        }
+        for addr in &self.bind {
+            parse_bind(addr)?;
+        }
+        if self.max_upload_size == 0 {
+            return Err(ConfigError::MaxUploadSize);
+        }
+        if self.log.level.parse::<tracing::Level>().is_err() {
+            return Err(ConfigError::LogLevel(self.log.level.clone()));
+        }
+        if let Some(tls) = &self.tls {
+            for path in [Some(&tls.cert), Some(&tls.key), tls.client_ca.as_ref()]
+                .into_iter()
+                .flatten()
+            {
+                if !path.is_file() {
+                    return Err(ConfigError::Tls(path.clone()));
+                }
+            }
+        }
+        for (i, tenant) in self.tenants.iter().enumerate() {
+            let valid = tenant
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if tenant.name.is_empty() || !valid {
+                return Err(ConfigError::TenantName(tenant.name.clone()));
+            }
+            if self.tenants[..i].iter().any(|t| t.name == tenant.name) {
+                return Err(ConfigError::DuplicateTenant(tenant.name.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// The addresses to listen on. Must be called on a validated config.
+    pub fn bind_addrs(&self) -> Vec<SocketAddr> {
+        self.bind.iter().map(|a| parse_bind(a).unwrap()).collect()
+    }
+
+    /// Whether requests have to carry a bearer token.
+    pub fn auth_required(&self) -> bool {
+        !self.auth.tokens.is_empty() || self.tenants.iter().any(|t| !t.tokens.is_empty())
+    }
+
+    /// Find the directory of a tenant, or of the single store when there is no tenants.
+    pub fn tenant_dir(&self, tenant: Option<&str>) -> Option<PathBuf> {
+        match tenant {
+            None if self.tenants.is_empty() => Some(self.data_dir.clone()),
+            Some(name) if self.tenants.iter().any(|t| t.name == name) => {
+                Some(self.data_dir.join(name))
+            }
+            _ => None,
+        }
+    }
+
+    /// The layout of the new stores of a tenant.
+    pub fn tenant_layout(&self, tenant: Option<&str>) -> Layout {
+        self.tenants
+            .iter()
+            .find(|t| Some(t.name.as_str()) == tenant)
+            .and_then(|t| t.layout)
+            .unwrap_or(self.layout)
+    }
+
+    /// Whether `token` grants access to `tenant`.
+    pub fn authorized(&self, tenant: Option<&str>, token: Option<&str>) -> bool {
+        if !self.auth_required() {
+            return true;
+        }
+        let Some(token) = token else {
+            return false;
+        };
+        self.auth.tokens.iter().any(|t| t == token)
+            || self
+                .tenants
+                .iter()
+                .filter(|t| Some(t.name.as_str()) == tenant)
+                .any(|t| t.tokens.iter().any(|t| t == token))
+    }
+}
+
+fn parse_bind(addr: &str) -> Result<SocketAddr, ConfigError> {
+    addr.to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .ok_or_else(|| ConfigError::Bind(addr.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults() {
+        let config: Config = toml::from_str("").unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.data_dir, PathBuf::from("data"));
+        assert_eq!(
+            config.bind_addrs(),
+            vec![SocketAddr::from(([127, 0, 0, 1], DEFAULT_PORT))]
+        );
+        assert!(!config.auth_required());
+        assert_eq!(config.tenant_dir(None), Some(PathBuf::from("data")));
+        assert!(config.authorized(None, None));
+    }
+
+    #[test]
+    fn invalid() {
+        let config: Result<Config, _> = toml::from_str("port = 80");
+        assert!(config.is_err());
+
+        let config: Config = toml::from_str("bind = []").unwrap();
+        assert!(matches!(config.validate(), Err(ConfigError::NoBind)));
+
+        let config: Config = toml::from_str("bind = [\"nowhere\"]").unwrap();
+        assert!(matches!(config.validate(), Err(ConfigError::Bind(_))));
+
+        let config: Config = toml::from_str("[log]\nlevel = \"loud\"").unwrap();
+        assert!(matches!(config.validate(), Err(ConfigError::LogLevel(_))));
+
+        let config: Config =
+            toml::from_str("[[tenants]]\nname = \"a\"\n[[tenants]]\nname = \"a\"").unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::DuplicateTenant(_))
+        ));
+
+        let config: Config = toml::from_str("[[tenants]]\nname = \"../a\"").unwrap();
+        assert!(matches!(config.validate(), Err(ConfigError::TenantName(_))));
+    }
+
+    #[test]
+    fn tenants() {
+        let config: Config = toml::from_str(
+            "[auth]\ntokens = [\"admin\"]\n[[tenants]]\nname = \"a\"\ntokens = [\"ta\"]\n[[tenants]]\nname = \"b\"",
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.tenant_dir(None), None);
+        assert_eq!(config.tenant_dir(Some("c")), None);
+        assert_eq!(config.tenant_dir(Some("a")), Some(PathBuf::from("data/a")));
+        assert!(config.authorized(Some("a"), Some("admin")));
+        assert!(config.authorized(Some("b"), Some("admin")));
+        assert!(config.authorized(Some("a"), Some("ta")));
+        assert!(!config.authorized(Some("b"), Some("ta")));
+        assert!(!config.authorized(Some("a"), None));
+    }
+
+    #[test]
+    fn layouts() {
+        let config: Config = toml::from_str(
+            "layout = \"rfc6962\"\n[[tenants]]\nname = \"a\"\nlayout = \"bit_reversed\"\n[[tenants]]\nname = \"b\"\n[[tenants]]\nname = \"c\"\nlayout = \"mmr\"",
+        )
+        .unwrap();
+        assert_eq!(config.tenant_layout(Some("a")), Layout::BitReversed);
+        assert_eq!(config.tenant_layout(Some("b")), Layout::Rfc6962);
+        assert_eq!(config.tenant_layout(Some("c")), Layout::Mmr);
+        let config: Result<Config, _> = toml::from_str("layout = \"sideways\"");
+        assert!(config.is_err());
+    }
+}
@@ -0,0 +1,226 @@
+//! Offline check of a data directory.
+//!
+//! Every blob of the latest store is read back and checked against its leaf hash and its proof
+//! against the root named by `HEAD`, the root is recomputed from the leaves, and the files neither
+//! the store nor the keyed store reference are reported.
+use crate::config::Config;
+use htree_challenge::tree::{HMap, Proof, SMap};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Issue {
+    /// The store file named by `HEAD` can't be read.
+    MissingStore {
+        root: String,
+    },
+    /// The store file named by `HEAD` isn't a store.
+    CorruptStore {
+        root: String,
+        error: String,
+    },
+    /// The file of the keyed store isn't one.
+    CorruptKeys {
+        error: String,
+    },
+    /// The root recomputed from the leaves is not the one of the store.
+    RootMismatch {
+        expected: String,
+        computed: String,
+    },
+    MissingBlob {
+        nth: usize,
+        hash: String,
+    },
+    HashMismatch {
+        nth: usize,
+        expected: String,
+        actual: String,
+    },
+    /// The proof of the element doesn't lead to the root named by `HEAD`.
+    BadProof {
+        nth: usize,
+    },
+    Orphan {
+        file: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub dir: PathBuf,
+    pub root: Option<String>,
+    pub size: usize,
+    pub issues: Vec<Issue>,
+}
+
+/// Check the store of `dir`, hashing the blobs with `jobs` threads.
+pub async fn check(dir: PathBuf, jobs: usize) -> Report {
+    let root = crate::current_root(&dir).await;
+    tokio::task::spawn_blocking(move || check_blocking(dir, root, jobs))
+        .await
+        .unwrap()
+}
+
+fn check_blocking(dir: PathBuf, root: Option<String>, jobs: usize) -> Report {
+    let mut issues = Vec::new();
+    let store: HMap<String> = match &root {
+        Some(root) => match fs::read(dir.join(format!("{}.store", root))) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                issues.push(Issue::CorruptStore {
+                    root: root.clone(),
+                    error: e.to_string(),
+                });
+                HMap::new()
+            }),
+            Err(_) => {
+                issues.push(Issue::MissingStore { root: root.clone() });
+                HMap::new()
+            }
+        },
+        None => HMap::new(),
+    };
+    let size = store.len();
+    let leaves: Vec<blake3::Hash> = (0..size).map(|i| store.get_hash(i).unwrap()).collect();
+
+    if let (Some(expected), Some(computed)) = (&root, store.layout().root_of(&leaves)) {
+        if computed.to_hex().as_str() != expected {
+            issues.push(Issue::RootMismatch {
+                expected: expected.clone(),
+                computed: computed.to_hex().to_string(),
+            });
+        }
+    }
+
+    // the proofs are built from the leaves, so they are checked against the root they should give.
+    let expected = root.as_deref().and_then(|r| blake3::Hash::from_hex(r).ok());
+    if let Some(root) = expected.filter(|_| !store.is_empty()) {
+        let jobs = jobs.max(1);
+        let chunk = (size + jobs - 1) / jobs;
+        let (dir, store, leaves) = (&dir, &store, &leaves);
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..size)
+                .step_by(chunk)
+                .map(|start| {
+                    scope.spawn(move || {
+                        // the proofs of the chunk at once, see `HMap::proofs`.
+                        let indices: Vec<_> = (start..size.min(start + chunk)).collect();
+                        let proofs = store.proofs(&indices);
+                        indices
+                            .into_iter()
+                            .zip(proofs)
+                            .filter_map(|(nth, proof)| {
+                                check_blob(dir, root, nth, leaves[nth], proof.unwrap())
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            for worker in workers {
+                issues.extend(worker.join().unwrap());
+            }
+        });
+    }
+
+    let mut known: HashSet<String> = leaves.iter().map(|h| h.to_hex().to_string()).collect();
+    known.insert(String::from("HEAD"));
+    known.insert(String::from(crate::roots::ROOTS));
+    if let Some(root) = &root {
+        known.insert(format!("{}.store", root));
+    }
+    // the files of the keyed store.
+    if let Ok(data) = fs::read(dir.join(crate::KEYS)) {
+        known.insert(String::from(crate::KEYS));
+        match serde_json::from_slice::<SMap<String>>(&data) {
+            Ok(keys) => known.extend(keys.hashes().map(|h| h.to_hex().to_string())),
+            Err(e) => issues.push(Issue::CorruptKeys {
+                error: e.to_string(),
+            }),
+        }
+    }
+    if let Ok(entries) = fs::read_dir(&dir) {
+        for entry in entries {
+            let file = entry.unwrap().file_name().to_string_lossy().into_owned();
+            if !known.contains(&file) {
+                issues.push(Issue::Orphan { file });
+            }
+        }
+    }
+
+    Report {
+        dir,
+        root,
+        size,
+        issues,
+    }
+}
+
+fn check_blob(
+    dir: &Path,
+    root: blake3::Hash,
+    nth: usize,
+    leaf: blake3::Hash,
+    proof: Proof,
+) -> Option<Issue> {
+    let Ok(hash) = hash_file(&dir.join(leaf.to_hex().as_str())) else {
+        return Some(Issue::MissingBlob {
+            nth,
+            hash: leaf.to_hex().to_string(),
+        });
+    };
+    if hash != leaf {
+        return Some(Issue::HashMismatch {
+            nth,
+            expected: leaf.to_hex().to_string(),
+            actual: hash.to_hex().to_string(),
+        });
+    }
+    if !proof.prove_on(hash).against(root) {
+        return Some(Issue::BadProof { nth });
+    }
+    None
+}
+
+fn hash_file(path: &Path) -> io::Result<blake3::Hash> {
+    let mut hasher = blake3::Hasher::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize())
+}
+
+/// Check every data directory of the server and print the reports, as JSON if `json` is set.
+/// Returns whether no issue was found.
+pub async fn run(config: &Config, json: bool, jobs: usize) -> bool {
+    let dirs = if config.tenants.is_empty() {
+        vec![config.data_dir.clone()]
+    } else {
+        config
+            .tenants
+            .iter()
+            .map(|t| config.data_dir.join(&t.name))
+            .collect()
+    };
+    let mut reports = Vec::new();
+    for dir in dirs {
+        reports.push(check(dir, jobs).await);
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports).unwrap());
+    } else {
+        for report in &reports {
+            println!(
+                "{}: {} elements, root {}",
+                report.dir.display(),
+                report.size,
+                report.root.as_deref().unwrap_or("none")
+            );
+            for issue in &report.issues {
+                println!("  {:?}", issue);
+            }
+        }
+    }
+    reports.iter().all(|r| r.issues.is_empty())
+}
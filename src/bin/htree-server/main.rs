@@ -0,0 +1,669 @@
+use clap::{Parser, Subcommand};
+use htree_challenge::tree::*;
+use salvo::conn::rustls::{Keycert, RustlsConfig};
+use salvo::fs::NamedFile;
+use salvo::http::Method;
+use salvo::prelude::*;
+use salvo::size_limiter::max_size;
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use tokio::fs;
+use tokio::sync::OwnedMutexGuard;
+
+mod config;
+mod fsck;
+mod roots;
+use config::{Config, ConfigError, Tls, DEFAULT_PORT};
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check the stored files against the stores, offline, then exit.
+    Fsck {
+        /// Print the report as JSON.
+        #[arg(long)]
+        json: bool,
+        /// Number of threads hashing the files. Defaults to the number of CPUs.
+        #[arg(long)]
+        jobs: Option<usize>,
+    },
+}
+
+#[derive(Parser)]
+struct ServerArgs {
+    #[command(subcommand)]
+    cmd: Option<Command>,
+    /// Address to listen on. Overrides `bind` from the config file.
+    server: Option<String>,
+    /// Port to listen on, with `SERVER`.
+    #[arg(requires = "server")]
+    port: Option<u16>,
+    /// TOML config file. See the README for its content and defaults.
+    #[arg(long, env = "HTREE_CONFIG")]
+    config: Option<PathBuf>,
+    /// Directory holding the stores and the uploaded files.
+    #[arg(long, env = "HTREE_DATA_DIR")]
+    data_dir: Option<PathBuf>,
+    /// Addresses to listen on, as `host:port`. Can be repeated or comma separated.
+    #[arg(long, env = "HTREE_BIND", value_delimiter = ',')]
+    bind: Vec<String>,
+    /// Maximum size in bytes of an upload request.
+    #[arg(long, env = "HTREE_MAX_UPLOAD_SIZE")]
+    max_upload_size: Option<u64>,
+    /// One of `error`, `warn`, `info`, `debug` or `trace`.
+    #[arg(long, env = "HTREE_LOG")]
+    log_level: Option<String>,
+    /// PEM certificate chain to serve HTTPS with. Requires `--key`.
+    #[arg(long, env = "HTREE_TLS_CERT", requires = "key")]
+    cert: Option<PathBuf>,
+    /// PEM private key of `--cert`.
+    #[arg(long, env = "HTREE_TLS_KEY", requires = "cert")]
+    key: Option<PathBuf>,
+    /// PEM bundle of CAs allowed to sign client certificates. When set, clients must
+    /// authenticate with a certificate (mTLS).
+    #[arg(long, env = "HTREE_TLS_CLIENT_CA", requires = "cert")]
+    client_ca: Option<PathBuf>,
+}
+
+impl ServerArgs {
+    /// Load the config file and apply the command line overrides.
+    fn config(self) -> Result<Config, ConfigError> {
+        let mut config = match &self.config {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+        if let Some(server) = self.server {
+            let port = self.port.unwrap_or(DEFAULT_PORT);
+            config.bind = if server.contains(':') {
+                vec![format!("[{}]:{}", server, port)]
+            } else {
+                vec![format!("{}:{}", server, port)]
+            };
+        } else if !self.bind.is_empty() {
+            config.bind = self.bind;
+        }
+        if let Some(data_dir) = self.data_dir {
+            config.data_dir = data_dir;
+        }
+        if let Some(max_upload_size) = self.max_upload_size {
+            config.max_upload_size = max_upload_size;
+        }
+        if let Some(level) = self.log_level {
+            config.log.level = level;
+        }
+        if let (Some(cert), Some(key)) = (self.cert, self.key) {
+            config.tls = Some(Tls {
+                cert,
+                key,
+                client_ca: self.client_ca,
+            });
+        }
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Build the rustls config from the certificate files.
+fn rustls_config(tls: &Tls) -> std::io::Result<RustlsConfig> {
+    let keycert = Keycert::new()
+        .cert_from_path(&tls.cert)?
+        .key_from_path(&tls.key)?;
+    let config = RustlsConfig::new(keycert);
+    Ok(match &tls.client_ca {
+        Some(ca) => config.client_auth_required(std::fs::read(ca)?),
+        None => config,
+    })
+}
+
+/// Resolve the tenant of the request and check its token.
+///
+/// The data directory of the tenant is inserted in the depot as `dir`, and the layout of its new
+/// stores as `layout`.
+struct Tenancy(Arc<Config>);
+
+#[handler]
+impl Tenancy {
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        ctrl: &mut FlowCtrl,
+    ) {
+        let tenant = req.header::<String>("x-htree-tenant");
+        let token = req
+            .header::<String>("authorization")
+            .and_then(|h| h.strip_prefix("Bearer ").map(str::to_owned));
+        if !self.0.authorized(tenant.as_deref(), token.as_deref()) {
+            res.render(StatusError::unauthorized());
+            ctrl.skip_rest();
+            return;
+        }
+        let Some(dir) = self.0.tenant_dir(tenant.as_deref()) else {
+            res.render(StatusError::not_found().brief("Unknown tenant."));
+            ctrl.skip_rest();
+            return;
+        };
+        if !fs::try_exists(&dir).await.unwrap() {
+            fs::create_dir_all(&dir).await.unwrap();
+        }
+        depot.insert("dir", dir);
+        depot.insert("layout", self.0.tenant_layout(tenant.as_deref()));
+    }
+}
+
+/// Load the store given by the `root` query, or the latest one when a `size` query of a `GET`
+/// asks for a past version of it: the latest store holds the whole history. Without both, the
+/// store is the empty one of an initialized data directory.
+///
+/// A store that doesn't exist is a 404 telling the current version of the store. A push only
+/// extends the latest store: another root is a 409 and a `size` query a 400. It holds the lock of
+/// the data directory until [save_store] is done.
+#[handler]
+async fn load_store(req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+    let dir = depot.get::<PathBuf>("dir").unwrap().clone();
+    let pushing = req.method() != Method::GET;
+    if pushing && req.queries().contains_key("size") {
+        res.render(StatusError::bad_request().brief("A push can't be made at a past size."));
+        ctrl.skip_rest();
+        return;
+    }
+    let root = match req.query::<usize>("size") {
+        Some(_) => current_root(&dir).await,
+        None => req.query::<String>("root"),
+    };
+    tracing::debug!("load_store: {:?}", root);
+    if root
+        .as_ref()
+        .is_some_and(|root| blake3::Hash::from_hex(root).is_err())
+    {
+        res.render(StatusError::bad_request().brief("The root must be a hex encoded hash."));
+        ctrl.skip_rest();
+        return;
+    }
+    let lock = if pushing {
+        // the upload is read first, so a slow one doesn't hold the other pushes.
+        let _ = req.form_data().await;
+        Some(store_lock(&dir).lock_owned().await)
+    } else {
+        None
+    };
+    if pushing && root.is_some() && root != current_root(&dir).await {
+        let brief = format!("The store changed, {}.", current_state(&dir).await);
+        res.render(StatusError::conflict().brief(brief));
+        ctrl.skip_rest();
+        return;
+    }
+    let store = match root {
+        Some(root) => read_store(&dir, &root).await,
+        None if current_root(&dir).await.is_none() && initialized(&dir).await => {
+            Some(HMap::with_layout(*depot.get::<Layout>("layout").unwrap()))
+        }
+        None => None,
+    };
+    let Some(store) = store else {
+        let brief = format!("No such store, {}.", current_state(&dir).await);
+        res.render(StatusError::not_found().brief(brief));
+        ctrl.skip_rest();
+        return;
+    };
+    depot.insert("store", store);
+    if let Some(lock) = lock {
+        depot.insert("lock", lock);
+    }
+}
+
+/// Held while the store of a data directory is loaded, pushed to and saved back, so concurrent
+/// pushes don't both extend the same version of it.
+fn store_lock(dir: &Path) -> Arc<tokio::sync::Mutex<()>> {
+    type Locks = std::sync::Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<()>>>>;
+    static LOCKS: OnceLock<Locks> = OnceLock::new();
+    let mut locks = LOCKS.get_or_init(Locks::default).lock().unwrap();
+    locks.entry(dir.to_owned()).or_default().clone()
+}
+
+/// The latest version of the store of a data directory, as announced by `GET /head`.
+#[derive(Serialize)]
+struct Head {
+    size: usize,
+    root: Option<String>,
+    /// Proof the store grew from the size given by the `from` query.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    consistency: Option<Consistency>,
+}
+
+/// Read the root of the latest store of a data directory, `None` when it's empty.
+///
+/// It's recorded in the `HEAD` file at each push. Directories written before it existed hold
+/// a single store, so it's used instead.
+async fn current_root(dir: &Path) -> Option<String> {
+    if let Ok(root) = fs::read_to_string(dir.join("HEAD")).await {
+        return Some(root.trim().to_owned()).filter(|root| !root.is_empty());
+    }
+    let mut entries = fs::read_dir(dir).await.ok()?;
+    while let Some(entry) = entries.next_entry().await.unwrap() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if let Some(root) = name.strip_suffix(".store") {
+            return Some(root.to_owned());
+        }
+    }
+    None
+}
+
+/// Whether the data directory holds a store, maybe empty: created by `POST /init`, or by a push
+/// of a version without it.
+async fn initialized(dir: &Path) -> bool {
+    fs::try_exists(dir.join("HEAD")).await.unwrap() || current_root(dir).await.is_some()
+}
+
+/// Read the store of a data directory having the root `root`, if it exists and is readable.
+async fn read_store(dir: &Path, root: &str) -> Option<HMap<String>> {
+    let data = fs::read(dir.join(format!("{}.store", root))).await.ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+/// Describe the latest version of the store of a data directory, to explain an error.
+async fn current_state(dir: &Path) -> String {
+    match current_root(dir).await {
+        Some(root) => {
+            let size = read_store(dir, &root).await.map_or(0, |store| store.len());
+            format!("the store has {} elements and the root {}", size, root)
+        }
+        None if initialized(dir).await => String::from("the store is empty"),
+        None => String::from("the store is not initialized, `POST /init` creates it"),
+    }
+}
+
+/// Create the empty store of a data directory. A push names the root of the store it extends,
+/// so it's the only way to start a new one.
+#[handler]
+async fn init(_req: &mut Request, depot: &mut Depot, res: &mut Response, _ctrl: &mut FlowCtrl) {
+    let dir = depot.get::<PathBuf>("dir").unwrap();
+    if initialized(dir).await {
+        let brief = format!("Already initialized, {}.", current_state(dir).await);
+        res.render(StatusError::conflict().brief(brief));
+        return;
+    }
+    tracing::info!("init {}", dir.display());
+    fs::write(dir.join("HEAD"), "").await.unwrap();
+    res.status_code(StatusCode::CREATED);
+    res.render(Json(Head {
+        size: 0,
+        root: None,
+        consistency: None,
+    }));
+}
+
+#[handler]
+async fn head(req: &mut Request, depot: &mut Depot, res: &mut Response, _ctrl: &mut FlowCtrl) {
+    let dir = depot.get::<PathBuf>("dir").unwrap();
+    let store: HMap<String> = match current_root(dir).await {
+        Some(root) => match read_store(dir, &root).await {
+            Some(store) => store,
+            None => {
+                tracing::error!("head: can't read the store {}", root);
+                res.render(
+                    StatusError::internal_server_error().brief("The latest store can't be read."),
+                );
+                return;
+            }
+        },
+        None => HMap::new(),
+    };
+    let consistency = match req.query::<usize>("from") {
+        Some(from) => {
+            let Some(consistency) = store.consistency(from) else {
+                res.render(StatusError::bad_request().brief("`from` is beyond the store size."));
+                return;
+            };
+            Some(consistency)
+        }
+        None => None,
+    };
+    res.render(Json(Head {
+        size: store.len(),
+        root: (!store.is_empty()).then(|| store.root().to_hex().to_string()),
+        consistency,
+    }));
+}
+
+#[handler]
+async fn save_store(
+    req: &mut Request,
+    depot: &mut Depot,
+    _res: &mut Response,
+    _ctrl: &mut FlowCtrl,
+) {
+    let root = depot.get::<blake3::Hash>("root").unwrap();
+    tracing::info!("save_store {}", root);
+    let dir = depot.get::<PathBuf>("dir").unwrap();
+    let old_root = req.query::<String>("root");
+    let path = dir.join(format!("{}.store", root.to_hex()));
+    let store = depot.get::<HMap<String>>("store").unwrap();
+    fs::write(path, serde_json::to_vec(store).unwrap())
+        .await
+        .unwrap();
+    fs::write(dir.join("HEAD"), root.to_hex().as_str())
+        .await
+        .unwrap();
+    roots::append(dir, &roots::Entry::now(store.len(), *root))
+        .await
+        .unwrap();
+    if let Some(old_path) = old_root.map(|r| dir.join(format!("{}.store", r))) {
+        fs::remove_file(old_path).await.unwrap();
+    }
+    let hash = depot.get::<blake3::Hash>("hash").unwrap();
+    fs::rename(
+        depot.get::<PathBuf>("file").unwrap(),
+        dir.join(hash.to_hex().as_str()),
+    )
+    .await
+    .unwrap();
+    depot.remove::<OwnedMutexGuard<()>>("lock");
+}
+
+/// The roots of the store after each push, oldest first, paginated by the `offset` and `limit`
+/// queries.
+#[handler]
+async fn get_roots(req: &mut Request, depot: &mut Depot, res: &mut Response, _ctrl: &mut FlowCtrl) {
+    let dir = depot.get::<PathBuf>("dir").unwrap();
+    let offset = req.query::<usize>("offset").unwrap_or(0);
+    let limit = req
+        .query::<usize>("limit")
+        .unwrap_or(roots::PAGE)
+        .min(roots::MAX_PAGE);
+    let entries = roots::read(dir).await.unwrap();
+    res.render(Json(roots::page(entries, offset, limit)));
+}
+
+#[handler]
+async fn get(req: &mut Request, depot: &mut Depot, res: &mut Response, _ctrl: &mut FlowCtrl) {
+    let store = depot.get::<HMap<String>>("store").unwrap();
+    let dir = depot.get::<PathBuf>("dir").unwrap();
+    let id = req.param("id").unwrap();
+    let size = req.query::<usize>("size").unwrap_or(store.len());
+    let name = store.get(id).filter(|_| id < size);
+    if let Some(name) = name {
+        NamedFile::builder(dir.join(store.get_hash(id).unwrap().to_hex().as_str()))
+            .attached_name(name)
+            .send(req.headers(), res)
+            .await;
+    } else {
+        res.render(StatusError::not_found());
+    }
+}
+
+/// The proof of an element, as JSON or in the compact encoding given by the `encoding` query:
+/// `bytes`, `hex` or `base64`.
+///
+/// With a `size` query, the proof is against the root the store had at this size.
+#[handler]
+async fn get_proof(req: &mut Request, depot: &mut Depot, res: &mut Response, _ctrl: &mut FlowCtrl) {
+    let store = depot.get::<HMap<String>>("store").unwrap();
+    let id = req.param("id").unwrap();
+    let ret = match req.query::<usize>("size") {
+        Some(size) => store.proof_at(id, size),
+        None => store.proof(id),
+    };
+    if let Some(proof) = ret {
+        // the compact encoding, see `Proof::to_bytes`.
+        match req.query::<String>("encoding").as_deref() {
+            Some("bytes") => {
+                res.add_header("content-type", "application/octet-stream", true)
+                    .unwrap();
+                res.write_body(proof.to_bytes()).unwrap();
+            }
+            Some("hex") => res.render(Text::Plain(proof.to_hex())),
+            Some("base64") => res.render(Text::Plain(proof.to_base64())),
+            _ => res.render(Json(proof)),
+        }
+    } else {
+        res.render(StatusError::not_found());
+    }
+}
+
+/// Push the uploaded file to the store. A malformed upload, or a file which doesn't match its
+/// hash, is a 400 and the store is left as is.
+#[handler]
+async fn push(req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+    let Some(Ok(hash)) = req.form::<String>("hash").await.map(blake3::Hash::from_hex) else {
+        res.render(StatusError::bad_request().brief("The hash must be a hex encoded hash."));
+        ctrl.skip_rest();
+        return;
+    };
+    tracing::info!("push: {}", hash);
+    let Some((file, Some(name))) = req.file("file").await.map(|f| (f, f.name())) else {
+        res.render(StatusError::bad_request().brief("A named file must be uploaded."));
+        ctrl.skip_rest();
+        return;
+    };
+    if blake3::hash(&fs::read(file.path()).await.unwrap()) != hash {
+        res.render(StatusError::bad_request().brief("The file doesn't match its hash."));
+        ctrl.skip_rest();
+        return;
+    }
+    let (path, name) = (file.path().clone(), name.to_string());
+    depot.insert("file", path);
+    depot.insert("hash", hash);
+    let store = depot.get_mut::<HMap<String>>("store").unwrap();
+    let proof = store.push(hash, name);
+    let root: blake3::Hash = *proof.prove_on(hash);
+    depot.insert("root", root);
+    res.render(Json(proof));
+}
+
+/// File of the keyed store of a data directory, next to the stores.
+const KEYS: &str = "keys.map";
+
+/// Held while a keyed store is read, updated and written back, so concurrent puts don't drop
+/// each other's keys.
+fn keys_lock() -> &'static tokio::sync::Mutex<()> {
+    static LOCK: OnceLock<tokio::sync::Mutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| tokio::sync::Mutex::new(()))
+}
+
+/// Read the keyed store of a data directory, empty when there is none. A file which can't be read
+/// is rendered as a server error.
+async fn load_keys(dir: &Path, res: &mut Response) -> Option<SMap<String>> {
+    let keys = match fs::read(dir.join(KEYS)).await {
+        Ok(data) => serde_json::from_slice(&data).map_err(|e| e.to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(SMap::new()),
+        Err(e) => Err(e.to_string()),
+    };
+    match keys {
+        Ok(keys) => Some(keys),
+        Err(e) => {
+            tracing::error!("load_keys: can't read {}: {}", KEYS, e);
+            res.render(
+                StatusError::internal_server_error().brief("The keyed store can't be read."),
+            );
+            None
+        }
+    }
+}
+
+/// Parse the `key` parameter of the path, rendering a bad request if it's not a hash.
+fn key_param(req: &mut Request, res: &mut Response) -> Option<blake3::Hash> {
+    let key = req
+        .param::<String>("key")
+        .and_then(|k| blake3::Hash::from_hex(k).ok());
+    if key.is_none() {
+        res.render(StatusError::bad_request().brief("The key must be a hex encoded hash."));
+    }
+    key
+}
+
+/// The keyed store of a data directory, as announced by `GET /keys`.
+#[derive(Serialize)]
+struct KeysHead {
+    size: usize,
+    root: String,
+}
+
+#[handler]
+async fn keys_head(
+    _req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+    _ctrl: &mut FlowCtrl,
+) {
+    let Some(keys) = load_keys(depot.get::<PathBuf>("dir").unwrap(), res).await else {
+        return;
+    };
+    res.render(Json(KeysHead {
+        size: keys.len(),
+        root: keys.root().to_hex().to_string(),
+    }));
+}
+
+#[handler]
+async fn put_key(req: &mut Request, depot: &mut Depot, res: &mut Response, _ctrl: &mut FlowCtrl) {
+    let Some(key) = key_param(req, res) else {
+        return;
+    };
+    let Some(Ok(hash)) = req.form::<String>("hash").await.map(blake3::Hash::from_hex) else {
+        res.render(StatusError::bad_request().brief("The hash must be a hex encoded hash."));
+        return;
+    };
+    tracing::info!("put_key: {} {}", key, hash);
+    let dir = depot.get::<PathBuf>("dir").unwrap();
+    let Some((file, Some(name))) = req.file("file").await.map(|f| (f, f.name())) else {
+        res.render(StatusError::bad_request().brief("A named file must be uploaded."));
+        return;
+    };
+    if blake3::hash(&fs::read(file.path()).await.unwrap()) != hash {
+        res.render(StatusError::bad_request().brief("The file doesn't match its hash."));
+        return;
+    }
+    let _guard = keys_lock().lock().await;
+    let Some(mut keys) = load_keys(dir, res).await else {
+        return;
+    };
+    let proof = keys.insert(key, hash, name.to_string());
+    fs::rename(file.path(), dir.join(hash.to_hex().as_str()))
+        .await
+        .unwrap();
+    fs::write(dir.join(KEYS), serde_json::to_vec(&keys).unwrap())
+        .await
+        .unwrap();
+    res.render(Json(proof));
+}
+
+#[handler]
+async fn get_key(req: &mut Request, depot: &mut Depot, res: &mut Response, _ctrl: &mut FlowCtrl) {
+    let Some(key) = key_param(req, res) else {
+        return;
+    };
+    let dir = depot.get::<PathBuf>("dir").unwrap();
+    let Some(keys) = load_keys(dir, res).await else {
+        return;
+    };
+    if let (Some(name), Some(hash)) = (keys.get(&key), keys.get_hash(&key)) {
+        NamedFile::builder(dir.join(hash.to_hex().as_str()))
+            .attached_name(name)
+            .send(req.headers(), res)
+            .await;
+    } else {
+        res.render(StatusError::not_found());
+    }
+}
+
+/// The proof of the presence or the absence of a key.
+#[handler]
+async fn get_key_proof(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+    _ctrl: &mut FlowCtrl,
+) {
+    let Some(key) = key_param(req, res) else {
+        return;
+    };
+    let Some(keys) = load_keys(depot.get::<PathBuf>("dir").unwrap(), res).await else {
+        return;
+    };
+    res.render(Json(keys.proof(key)));
+}
+
+fn router(config: Arc<Config>) -> Router {
+    let max_upload_size = config.max_upload_size;
+    Router::with_hoop(Tenancy(config))
+        .push(
+            Router::with_hoop(max_size(max_upload_size))
+                .hoop(load_store)
+                .hoop(push)
+                .post(save_store),
+        )
+        .push(Router::with_path("init").post(init))
+        .push(Router::with_path("head").get(head))
+        .push(Router::with_path("roots").get(get_roots))
+        .push(
+            Router::with_path("keys").get(keys_head).push(
+                Router::with_path("<key>")
+                    .get(get_key)
+                    .push(Router::with_hoop(max_size(max_upload_size)).put(put_key))
+                    .push(Router::with_path("proof").get(get_key_proof)),
+            ),
+        )
+        .push(
+            Router::with_path("<id: num>")
+                .hoop(load_store)
+                .get(get)
+                .push(Router::with_path("proof").get(get_proof)),
+        )
+}
+
+/// Listen on `addr` until the process is stopped.
+async fn serve(addr: SocketAddr, tls: Option<RustlsConfig>, router: Router) {
+    let listener = TcpListener::new(addr);
+    if let Some(tls) = tls {
+        let acceptor = listener.rustls(tls).bind().await;
+        Server::new(acceptor).serve(router).await;
+    } else {
+        let acceptor = listener.bind().await;
+        Server::new(acceptor).serve(router).await;
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let mut args = ServerArgs::parse();
+    let cmd = args.cmd.take();
+    let config = args.config().unwrap_or_else(|e| {
+        eprintln!("htree-server: {}", e);
+        std::process::exit(1);
+    });
+    if let Some(Command::Fsck { json, jobs }) = cmd {
+        let jobs = jobs.unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
+        let clean = fsck::run(&config, json, jobs).await;
+        std::process::exit(if clean { 0 } else { 1 });
+    }
+    tracing_subscriber::fmt()
+        .with_max_level(config.log.level.parse::<tracing::Level>().unwrap())
+        .init();
+    let tls = config.tls.as_ref().map(|tls| {
+        rustls_config(tls).unwrap_or_else(|e| {
+            eprintln!("htree-server: can't load TLS certificates: {}", e);
+            std::process::exit(1);
+        })
+    });
+    let config = Arc::new(config);
+    // the futures of salvo's servers can't be proven `Send` by recent compilers, so the accept
+    // loops run on this thread while the connections are still handled by the whole runtime.
+    let servers = tokio::task::LocalSet::new();
+    let handles: Vec<_> = config
+        .bind_addrs()
+        .into_iter()
+        .map(|addr| servers.spawn_local(serve(addr, tls.clone(), router(config.clone()))))
+        .collect();
+    servers
+        .run_until(async {
+            for handle in handles {
+                handle.await.unwrap();
+            }
+        })
+        .await;
+}
@@ -0,0 +1,122 @@
+//! Append-only log of the roots of a store.
+//!
+//! Each push appends the size and the root of the new store, and the time of the push, as a JSON
+//! line to the `roots.log` file of the data directory. Stores pushed to before the log existed
+//! only have their later roots in it.
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs;
+
+/// File of the log, in the data directory.
+pub const ROOTS: &str = "roots.log";
+
+/// Number of roots of a page of `GET /roots` by default, and at most.
+pub const PAGE: usize = 100;
+pub const MAX_PAGE: usize = 1000;
+
+/// A version of the store.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub size: usize,
+    /// The root, as a hex string.
+    pub root: String,
+    /// Unix time of the push.
+    pub timestamp: u64,
+}
+
+/// A page of the log, as returned by `GET /roots`.
+#[derive(Debug, Serialize)]
+pub struct Page {
+    /// The number of roots in the whole log.
+    pub total: usize,
+    pub offset: usize,
+    pub roots: Vec<Entry>,
+}
+
+impl Entry {
+    /// The entry of the store of `size` elements with the root `root`, pushed now.
+    pub fn now(size: usize, root: blake3::Hash) -> Self {
+        Self {
+            size,
+            root: root.to_hex().to_string(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        }
+    }
+}
+
+/// Append `entry` to the log of `dir`.
+pub async fn append(dir: &Path, entry: &Entry) -> io::Result<()> {
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+    let path = dir.join(ROOTS);
+    tokio::task::spawn_blocking(move || {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        file.write_all(&line)?;
+        file.sync_all()
+    })
+    .await
+    .unwrap()
+}
+
+/// Read the log of `dir`, oldest first. Empty when there is none.
+pub async fn read(dir: &Path) -> io::Result<Vec<Entry>> {
+    let log = match fs::read_to_string(dir.join(ROOTS)).await {
+        Ok(log) => log,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    log.lines()
+        .map(|line| serde_json::from_str(line).map_err(io::Error::from))
+        .collect()
+}
+
+/// The `limit` entries starting at `offset`.
+pub fn page(mut entries: Vec<Entry>, offset: usize, limit: usize) -> Page {
+    let total = entries.len();
+    entries.drain(..offset.min(total));
+    entries.truncate(limit);
+    Page {
+        total,
+        offset,
+        roots: entries,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn log() {
+        let dir = std::env::temp_dir().join(format!("htree-roots-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir).await;
+        fs::create_dir_all(&dir).await.unwrap();
+        assert_eq!(read(&dir).await.unwrap(), vec![]);
+        let entries: Vec<Entry> = (1..=3)
+            .map(|size| Entry::now(size, blake3::hash(&[size as u8])))
+            .collect();
+        for entry in &entries {
+            append(&dir, entry).await.unwrap();
+        }
+        assert_eq!(read(&dir).await.unwrap(), entries);
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[test]
+    fn pages() {
+        let entries: Vec<Entry> = (1..=5)
+            .map(|size| Entry::now(size, blake3::hash(&[size as u8])))
+            .collect();
+        let page2 = page(entries.clone(), 2, 2);
+        assert_eq!((page2.total, page2.offset), (5, 2));
+        assert_eq!(page2.roots, entries[2..4]);
+        assert_eq!(page(entries.clone(), 4, 2).roots, entries[4..]);
+        assert!(page(entries, 7, 2).roots.is_empty());
+    }
+}
@@ -1 +1,7 @@
+//! Without the `std` feature, the library is `no_std` and only needs `alloc`: stores can be
+//! built and proofs verified on embedded targets or in wasm.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
 pub mod tree;
@@ -25,32 +25,198 @@
 //! assert!(store.proof(i).unwrap().prove_on(blake3::hash(data[i])).against(root))
 //! }
 //! ```
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+pub use core::hash::Hash;
+pub use core::ops::Deref;
 pub use serde::{Deserialize, Serialize};
-pub use std::hash::Hash;
-pub use std::ops::Deref;
+
+mod bit_reversed;
+mod collection;
+mod encoding;
+mod hash;
+mod leaf;
+mod levels;
+mod mmr;
+mod parallel;
+mod rfc6962;
+mod sparse;
+mod verify;
+
+pub use collection::{Iter, IterProofs};
+pub use encoding::{proof_base64, proof_hex, DecodeError};
+pub use leaf::MerkleLeaf;
+pub use sparse::{KeyProof, SMap};
+pub use verify::{VerifiedInclusion, VerifyError};
 
 /// The merkel tree storage.
-#[derive(Debug, Default, Serialize, Deserialize)]
-pub struct HMap<D: Hash> {
+#[derive(Debug, Default, Deserialize)]
+#[serde(from = "Stored<D>")]
+pub struct HMap<D> {
     data: Vec<D>,
     tree: Tree,
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
-enum Tree {
+/// How the elements of a [HMap] are placed in its tree.
+///
+/// The layout is chosen at the creation of a store (see [HMap::with_layout]) and is carried by
+/// its proofs, so they are verified accordingly.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Layout {
+    /// The `nth` element is found by following the bits of `nth` from the least significant
+    /// one (`0` is left). Nodes are hashed with BLAKE3 and leaves are the hashes of the
+    /// elements.
     #[default]
+    BitReversed,
+    /// The left-filled tree of RFC 6962 (Certificate Transparency): elements are in insertion
+    /// order, leaves are `SHA-256(0x00 || hash)` and nodes `SHA-256(0x01 || left || right)`.
+    ///
+    /// Proofs are the audit paths and consistency proofs of the RFC, so they can be checked by
+    /// CT-style verifiers, the leaf data being the 32 bytes of the element hash.
+    Rfc6962,
+    /// A Merkle Mountain Range: a list of perfect trees whose peaks are bagged into the root.
+    ///
+    /// Pushes are O(1) amortized and the nodes never change once built, which suits append
+    /// heavy stores such as logs. Nodes are hashed with BLAKE3 and leaves are the hashes of the
+    /// elements.
+    Mmr,
+}
+
+/// The tree of a [HMap], its nodes being placed according to its [Layout].
+///
+/// Only the leaves are stored, the nodes are rebuilt when loading.
+#[derive(Debug, Clone, PartialEq)]
+enum Tree {
+    BitReversed(bit_reversed::BitReversed),
+    /// The hashes of the elements, and the perfect subtrees of their leaf hashes of the RFC.
+    Rfc6962 {
+        leaves: Vec<blake3::Hash>,
+        subtrees: rfc6962::Subtrees,
+    },
+    Mmr(mmr::Mmr),
+}
+
+impl<D: Serialize> Serialize for HMap<D> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut store = serializer.serialize_struct("HMap", 3)?;
+        store.serialize_field("data", &self.data)?;
+        store.serialize_field(
+            "tree",
+            &LeavesRef {
+                leaves: self.tree.leaves(),
+            },
+        )?;
+        store.serialize_field("layout", &self.tree.layout())?;
+        store.end()
+    }
+}
+
+// The leaves of a tree, written as a [Leaves] without copying them.
+#[derive(Serialize)]
+struct LeavesRef<'a> {
+    #[serde(serialize_with = "hash_vec_ser")]
+    leaves: &'a [blake3::Hash],
+}
+
+#[derive(Deserialize)]
+struct Leaves {
+    #[serde(deserialize_with = "hash_vec_deser")]
+    leaves: Vec<blake3::Hash>,
+}
+
+// A store as read, its tree being migrated from the nested form of older versions according to
+// its layout.
+#[derive(Deserialize)]
+struct Stored<D> {
+    data: Vec<D>,
+    tree: StoredTree,
+    #[serde(default)]
+    layout: Layout,
+    /// The leaves of the [Layout::Mmr] older versions wrote next to an empty tree.
+    #[serde(default)]
+    mmr: Option<Leaves>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredTree {
+    Flat(Leaves),
+    Nested(Nested),
+}
+
+// The boxed tree older versions wrote, only read to be migrated.
+#[derive(Deserialize)]
+enum Nested {
     Empty,
     Leaf {
         #[serde(deserialize_with = "hash_deser")]
-        #[serde(serialize_with = "hash_ser")]
         hash: blake3::Hash,
     },
     Node {
-        left: Box<Tree>,
-        right: Box<Tree>,
+        left: Box<Nested>,
+        right: Box<Nested>,
     },
 }
 
+impl Nested {
+    /// Collect the leaves from left to right.
+    fn entries(self, entries: &mut Vec<blake3::Hash>) {
+        match self {
+            Nested::Node { left, right } => {
+                left.entries(entries);
+                right.entries(entries);
+            }
+            Nested::Leaf { hash } => entries.push(hash),
+            Nested::Empty => (),
+        }
+    }
+
+    /// Collect the leaves of this subtree of the [Layout::BitReversed] holding the elements
+    /// `offset + k * stride`, as `(index, hash)`.
+    fn bit_reversed(self, offset: usize, stride: usize, entries: &mut Vec<(usize, blake3::Hash)>) {
+        match self {
+            Nested::Node { left, right } => {
+                left.bit_reversed(offset, stride * 2, entries);
+                right.bit_reversed(offset + stride, stride * 2, entries);
+            }
+            Nested::Leaf { hash } => entries.push((offset, hash)),
+            Nested::Empty => (),
+        }
+    }
+
+    /// The leaves in insertion order.
+    fn leaves(self, layout: Layout) -> Vec<blake3::Hash> {
+        let mut leaves = vec![];
+        if layout == Layout::BitReversed {
+            let mut entries = vec![];
+            self.bit_reversed(0, 1, &mut entries);
+            entries.sort_unstable_by_key(|(nth, _)| *nth);
+            leaves.extend(entries.into_iter().map(|(_, hash)| hash));
+        } else {
+            self.entries(&mut leaves);
+        }
+        leaves
+    }
+}
+
+impl<D> From<Stored<D>> for HMap<D> {
+    fn from(stored: Stored<D>) -> Self {
+        let leaves = match (stored.layout, stored.mmr, stored.tree) {
+            (Layout::Mmr, Some(Leaves { leaves }), _) => leaves,
+            (_, _, StoredTree::Flat(Leaves { leaves })) => leaves,
+            (_, _, StoredTree::Nested(tree)) => tree.leaves(stored.layout),
+        };
+        HMap {
+            data: stored.data,
+            tree: Tree::new(stored.layout, leaves),
+        }
+    }
+}
+
 /// A proof used to check data are not corupted.
 ///
 /// This can be obtained by a call to [HMap::get].
@@ -58,33 +224,105 @@ enum Tree {
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Proof {
     nth: usize,
+    #[serde(default, skip_serializing_if = "Layout::is_bit_reversed")]
+    layout: Layout,
+    /// Size of the store the proof was made for. Proofs of the [Layout::BitReversed] made by
+    /// older versions don't have it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<usize>,
     #[serde(deserialize_with = "hash_vec_deser")]
     #[serde(serialize_with = "hash_vec_ser")]
     hashes: Vec<blake3::Hash>,
 }
 
+// Hashes are serialized as hex strings. Older versions wrote them as arrays of 32 bytes, which
+// are still accepted.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HashRepr {
+    Hex(String),
+    Bytes([u8; 32]),
+}
+
+impl HashRepr {
+    fn into_hash<E: serde::de::Error>(self) -> Result<blake3::Hash, E> {
+        match self {
+            HashRepr::Hex(hex) => blake3::Hash::from_hex(hex).map_err(E::custom),
+            HashRepr::Bytes(bytes) => Ok(blake3::Hash::from_bytes(bytes)),
+        }
+    }
+}
+
 // serialize helper for `Vec<blake3::hASH>`
 fn hash_vec_ser<S: serde::Serializer>(
     vec: &[blake3::Hash],
     serializer: S,
 ) -> Result<S::Ok, S::Error> {
-    let vec2: Vec<[u8; 32]> = vec.iter().map(|h| *h.as_bytes()).collect();
-
-    vec2.serialize(serializer)
+    serializer.collect_seq(vec.iter().map(|h| h.to_hex().to_string()))
 }
 fn hash_vec_deser<'de, D: serde::Deserializer<'de>>(
     deserializer: D,
 ) -> Result<Vec<blake3::Hash>, D::Error> {
-    let vec: Vec<[u8; 32]> = Deserialize::deserialize(deserializer)?;
-    Ok(vec.iter().map(|h| blake3::Hash::from_bytes(*h)).collect())
+    let vec: Vec<HashRepr> = Deserialize::deserialize(deserializer)?;
+    vec.into_iter().map(HashRepr::into_hash).collect()
 }
 // serialize helper for `blake3::hASH`
 fn hash_ser<S: serde::Serializer>(hash: &blake3::Hash, serializer: S) -> Result<S::Ok, S::Error> {
-    hash.as_bytes().serialize(serializer)
+    serializer.serialize_str(hash.to_hex().as_str())
 }
 fn hash_deser<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<blake3::Hash, D::Error> {
-    let bytes: [u8; 32] = Deserialize::deserialize(deserializer)?;
-    Ok(blake3::Hash::from_bytes(bytes))
+    HashRepr::deserialize(deserializer)?.into_hash()
+}
+
+/// A proof that a store is an extension of an older version of itself.
+///
+/// With the [Layout::BitReversed], it holds for every element pushed since the old version the
+/// hash of the element and the proof returned by [HMap::push] when it was inserted, or only the
+/// new size from the empty store, which anything extends. With the
+/// [Layout::Rfc6962], it's the consistency proof of the RFC. With the [Layout::Mmr], it's the
+/// ancestry proof: the old peaks and the nodes needed to climb from them to the new peaks. It
+/// can be obtained by a call to [HMap::consistency].
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Consistency {
+    from: usize,
+    #[serde(flatten)]
+    kind: ConsistencyKind,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum ConsistencyKind {
+    /// [Layout::BitReversed]: the proof of every element pushed since.
+    Steps { steps: Vec<Step> },
+    /// [Layout::Mmr]: the peaks at `from` and the ancestry proof to the size `to`. Before
+    /// [ConsistencyKind::Rfc6962] which would match it too.
+    Mmr {
+        to: usize,
+        #[serde(deserialize_with = "hash_vec_deser")]
+        #[serde(serialize_with = "hash_vec_ser")]
+        peaks: Vec<blake3::Hash>,
+        #[serde(deserialize_with = "hash_vec_deser")]
+        #[serde(serialize_with = "hash_vec_ser")]
+        hashes: Vec<blake3::Hash>,
+    },
+    /// [Layout::Rfc6962]: the consistency proof of the RFC to the size `to`.
+    Rfc6962 {
+        to: usize,
+        #[serde(deserialize_with = "hash_vec_deser")]
+        #[serde(serialize_with = "hash_vec_ser")]
+        hashes: Vec<blake3::Hash>,
+    },
+    /// [Layout::BitReversed] from the empty store: the size `to` only. Last, as it would match
+    /// any of the others.
+    Empty { to: usize },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Step {
+    #[serde(deserialize_with = "hash_deser")]
+    #[serde(serialize_with = "hash_ser")]
+    hash: blake3::Hash,
+    proof: Proof,
 }
 
 /// A "hashed" proof with the hash of the challenged data.
@@ -99,19 +337,34 @@ impl Proof {
     /// Perform the proof over a [Hash](blake3::Hash). Thesh parameter is the one of the data we
     /// want to check the authenticity.
     pub fn prove_on(&self, hash: blake3::Hash) -> PartialProof {
-        let Proof { nth, hashes } = self;
-        let len = hashes.len();
-        let mut mask = if len > 0 { 0x1 << (len - 1) } else { 0 };
-        PartialProof(hashes.iter().rfold(hash, |ag, h| {
-            let mut hasher = blake3::Hasher::new();
-            if nth & mask > 0 {
-                hasher.update(h.as_bytes()).update(ag.as_bytes());
+        PartialProof(self.root_at(hash, self.size.unwrap_or(0)))
+    }
+
+    // The root recomputed from the hash of the element, in a store of `size` elements. Only the
+    // layouts whose proofs depend on it use the size.
+    fn root_at(&self, hash: blake3::Hash, size: usize) -> blake3::Hash {
+        match self.layout {
+            Layout::Rfc6962 => {
+                return rfc6962::root_from_path(
+                    rfc6962::leaf_hash(hash.as_bytes()),
+                    self.nth,
+                    size,
+                    &self.hashes,
+                )
+            }
+            Layout::Mmr => return mmr::root_from_path(hash, self.nth, size, &self.hashes),
+            Layout::BitReversed => (),
+        }
+        let Proof { nth, hashes, .. } = self;
+        hashes.iter().enumerate().rfold(hash, |ag, (i, h)| {
+            // the bits of `nth` beyond its width are 0, a forged proof can be longer.
+            let bit = u32::try_from(i).ok().and_then(|i| nth.checked_shr(i));
+            if bit.unwrap_or(0) & 1 == 1 {
+                hash::node_hash(h, &ag)
             } else {
-                hasher.update(ag.as_bytes()).update(h.as_bytes());
+                hash::node_hash(&ag, h)
             }
-            mask >>= 1;
-            hasher.finalize()
-        }))
+        })
     }
 
     /// Performs a hashing of this proof.
@@ -127,17 +380,172 @@ impl Proof {
     ///
     /// TODO
     pub fn hash(&self) -> Option<blake3::Hash> {
+        if self.layout != Layout::BitReversed {
+            // only the proof of the last element is made of the roots of the perfect subtrees
+            // of the store before its insertion, from the right.
+            if self.size.and_then(|size| size.checked_sub(1)) != Some(self.nth) {
+                return None;
+            }
+            let (first, rest) = self.hashes.split_first()?;
+            let node_hash = match self.layout {
+                Layout::Rfc6962 => rfc6962::node_hash,
+                _ => hash::node_hash,
+            };
+            return Some(rest.iter().fold(*first, |r, h| node_hash(h, &r)));
+        }
         // could be implemented without cloning.
-        let Proof { nth, hashes } = self;
+        let Proof { nth, hashes, .. } = self;
         let mut hashes = hashes.clone();
         let hash = hashes.pop();
-        hash.map(|hash| *Proof { nth: *nth, hashes }.prove_on(hash))
+        hash.map(|hash| {
+            *Proof {
+                nth: *nth,
+                layout: Layout::BitReversed,
+                size: None,
+                hashes,
+            }
+            .prove_on(hash)
+        })
     }
 
     /// Return the indice of the data prooved by this `Proof`
     pub fn nth(&self) -> usize {
         self.nth
     }
+
+    /// Return the layout of the store this `Proof` comes from.
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Return the size of the store this `Proof` was made for, if known.
+    pub fn size(&self) -> Option<usize> {
+        self.size
+    }
+}
+
+impl Layout {
+    fn is_bit_reversed(&self) -> bool {
+        *self == Layout::BitReversed
+    }
+
+    /// The number of hashes of the proof of the `nth` element in a store of `size` elements,
+    /// for `nth < size`.
+    fn proof_len(self, nth: usize, size: usize) -> usize {
+        match self {
+            Layout::BitReversed => {
+                // the subtree holding `nth` holds the elements `offset + k * stride`.
+                let (mut offset, mut stride, mut len) = (0, 1usize, 0);
+                // `stride` overflows to 0 past the last level of the largest sizes.
+                while stride != 0 && offset + stride < size {
+                    if nth & stride > 0 {
+                        offset += stride;
+                    }
+                    stride <<= 1;
+                    len += 1;
+                }
+                len
+            }
+            Layout::Rfc6962 => rfc6962::path_len(nth, size),
+            Layout::Mmr => mmr::path_len(nth, size),
+        }
+    }
+
+    /// Compute the root of a store holding elements with these hashes, in insertion order,
+    /// without building it. Returns `None` when there is no element.
+    ///
+    /// It's equivalent to pushing the hashes in an empty [HMap] and calling [HMap::root], but
+    /// in linear time.
+    pub fn root_of(self, hashes: &[blake3::Hash]) -> Option<blake3::Hash> {
+        // root of the subtree holding the elements `offset + k * stride`.
+        fn subroot(hashes: &[blake3::Hash], offset: usize, stride: usize) -> blake3::Hash {
+            if offset + stride >= hashes.len() {
+                return hashes[offset];
+            }
+            let (left, right) = parallel::join(
+                (hashes.len() - offset) / stride,
+                || subroot(hashes, offset, stride * 2),
+                || subroot(hashes, offset + stride, stride * 2),
+            );
+            hash::node_hash(&left, &right)
+        }
+        if hashes.is_empty() {
+            return None;
+        }
+        Some(match self {
+            Layout::BitReversed => subroot(hashes, 0, 1),
+            Layout::Rfc6962 => rfc6962::root(&parallel::map(hashes, parallel::MIN_LEN, |h| {
+                rfc6962::leaf_hash(h.as_bytes())
+            })),
+            Layout::Mmr => mmr::Mmr::from_leaves(hashes.to_vec()).root(hashes.len())?,
+        })
+    }
+}
+
+impl Consistency {
+    /// Check the store went from `old_root`, the trusted root of its version of `old_size`
+    /// elements, to `new_root` only by pushing new elements, its elements being placed with
+    /// `layout`.
+    ///
+    /// `old_root` is `None` for an empty store. A proof from another size or for another
+    /// layout is rejected, so the server can't make the old root stand for another version of
+    /// the store.
+    pub fn verify(
+        &self,
+        old_size: usize,
+        old_root: Option<blake3::Hash>,
+        new_root: blake3::Hash,
+        layout: Layout,
+    ) -> bool {
+        if self.from != old_size || self.layout() != layout {
+            return false;
+        }
+        match (&self.kind, old_root) {
+            (ConsistencyKind::Steps { steps }, _) => {
+                let mut root = old_root;
+                for (i, Step { hash, proof }) in steps.iter().enumerate() {
+                    match proof.verify_append(*hash, self.from + i, root, layout) {
+                        Ok(verified) => root = Some(verified.root()),
+                        Err(_) => return false,
+                    }
+                }
+                root == Some(new_root)
+            }
+            // anything is an extension of the empty store.
+            (ConsistencyKind::Rfc6962 { hashes, .. }, None) => self.from == 0 && hashes.is_empty(),
+            (ConsistencyKind::Rfc6962 { to, hashes }, Some(old_root)) => {
+                rfc6962::verify_consistency(self.from, *to, old_root, new_root, hashes)
+            }
+            (ConsistencyKind::Mmr { to, peaks, hashes }, _) => {
+                mmr::verify_ancestry(self.from, *to, old_root, new_root, peaks, hashes)
+            }
+            (ConsistencyKind::Empty { .. }, old_root) => self.from == 0 && old_root.is_none(),
+        }
+    }
+
+    /// The layout of the store the proof claims to be made for.
+    pub fn layout(&self) -> Layout {
+        match &self.kind {
+            ConsistencyKind::Steps { .. } | ConsistencyKind::Empty { .. } => Layout::BitReversed,
+            ConsistencyKind::Rfc6962 { .. } => Layout::Rfc6962,
+            ConsistencyKind::Mmr { .. } => Layout::Mmr,
+        }
+    }
+
+    /// The size of the old version of the store.
+    pub fn from(&self) -> usize {
+        self.from
+    }
+
+    /// The size of the new version of the store.
+    pub fn to(&self) -> usize {
+        match &self.kind {
+            ConsistencyKind::Steps { steps } => self.from + steps.len(),
+            ConsistencyKind::Rfc6962 { to, .. }
+            | ConsistencyKind::Mmr { to, .. }
+            | ConsistencyKind::Empty { to } => *to,
+        }
+    }
 }
 
 impl PartialProof {
@@ -153,83 +561,173 @@ impl Deref for PartialProof {
     }
 }
 
-impl<D: Hash + Clone> HMap<D> {
+impl<D> HMap<D> {
     /// Create an empty storage.
     pub fn new() -> Self {
+        Self::with_layout(Layout::BitReversed)
+    }
+
+    /// Create an empty storage placing its elements with the given [Layout].
+    pub fn with_layout(layout: Layout) -> Self {
         Self {
             data: vec![],
-            tree: Tree::Empty,
+            tree: Tree::new(layout, vec![]),
+        }
+    }
+
+    /// Create a storage placing its elements with the given [Layout], holding the `(hash,
+    /// element)` pairs in order.
+    ///
+    /// It's the store pushing them one by one gives, but built from the leaves up in linear
+    /// time, and in parallel with the `parallel` feature.
+    pub fn from_leaves<I>(layout: Layout, leaves: I) -> Self
+    where
+        I: IntoIterator<Item = (blake3::Hash, D)>,
+    {
+        let (hashes, data): (Vec<_>, Vec<_>) = leaves.into_iter().unzip();
+        HMap {
+            data,
+            tree: Tree::new(layout, hashes),
         }
     }
 
+    pub fn layout(&self) -> Layout {
+        self.tree.layout()
+    }
+
     /// Compute the root of the full underlying merkel tree.
     ///
     /// In a client server environment, it can only be computed by the server and so is not
     /// confiable by the client.
+    ///
+    /// # Panics
+    ///
+    /// If the store is empty.
     pub fn root(&self) -> blake3::Hash {
-        self.tree.hash()
+        let root = match &self.tree {
+            Tree::BitReversed(tree) => tree.root(),
+            Tree::Rfc6962 { subtrees, .. } => subtrees.root(self.data.len()),
+            Tree::Mmr(mmr) => mmr.root(self.data.len()),
+        };
+        root.expect("empty store")
+    }
+
+    /// The number of elements in the store.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
     }
 
     /// Push an element to the store and returns it's proof.
     pub fn push(&mut self, hash: blake3::Hash, data: D) -> Proof {
         let nth = self.data.len();
-        let mut pos = nth;
-        let mut hashes = Vec::new();
-        let mut current_node = &mut self.tree;
-        while let Tree::Node { left, right } = current_node {
-            if pos & 0x1 > 0 {
-                hashes.push(left.hash());
-                current_node = right;
-            } else {
-                hashes.push(right.hash());
-                current_node = left;
+        match &mut self.tree {
+            Tree::BitReversed(tree) => tree.push(hash),
+            Tree::Rfc6962 { leaves, subtrees } => {
+                leaves.push(hash);
+                subtrees.push(rfc6962::leaf_hash(hash.as_bytes()));
             }
-            pos >>= 1;
-        }
-        if !self.data.is_empty() {
-            hashes.push(current_node.hash());
+            Tree::Mmr(mmr) => mmr.push(hash),
         }
-        *current_node = current_node.clone().merge(Tree::Leaf { hash });
         self.data.push(data);
-        Proof { nth, hashes }
+        self.proof(nth).unwrap()
     }
 
     /// Returns the proof ot the `nth` element of the store.
     pub fn proof(&self, nth: usize) -> Option<Proof> {
-        if nth >= self.data.len() {
+        self.proof_at(nth, self.data.len())
+    }
+
+    /// Returns the proof of the `nth` element as it was when the store had `size` elements,
+    /// to prove it against a past root.
+    ///
+    /// With the [Layout::BitReversed], the nodes holding the `k` elements pushed since are
+    /// rehashed, `O(k log n)` hashes. The other layouts only read `O(log n)` nodes.
+    pub fn proof_at(&self, nth: usize, size: usize) -> Option<Proof> {
+        if nth >= size || size > self.data.len() {
             return None;
         }
-        let mut current_node = &self.tree;
-        let mut pos = nth;
-        let mut hashes = Vec::new();
-        while let Tree::Node { left, right } = current_node {
-            if pos & 0x1 > 0 {
-                hashes.push(left.hash());
-                current_node = right.as_ref();
-            } else {
-                hashes.push(right.hash());
-                current_node = left.as_ref();
-            }
-            pos >>= 1;
-        }
-        Some(Proof { nth, hashes })
+        let hashes = match &self.tree {
+            Tree::BitReversed(tree) => tree.past(size).path(nth),
+            Tree::Rfc6962 { subtrees, .. } => subtrees.path(nth, size),
+            Tree::Mmr(mmr) => mmr.path(nth, size),
+        };
+        Some(Proof {
+            nth,
+            layout: self.layout(),
+            size: Some(size),
+            hashes,
+        })
     }
 
-    pub fn get_hash(&self, mut nth: usize) -> Option<blake3::Hash> {
-        if nth >= self.data.len() {
+    /// Returns the proofs of the elements at `indices`, `None` for the ones beyond the store.
+    ///
+    /// They are computed in parallel with the `parallel` feature.
+    pub fn proofs(&self, indices: &[usize]) -> Vec<Option<Proof>>
+    where
+        D: Sync,
+    {
+        parallel::map(indices, 1, |&nth| self.proof(nth))
+    }
+
+    /// Returns the proof this store has grown from the version having `from` elements.
+    ///
+    /// It's made of `O(k log n)` hashes for `k` elements pushed since with the
+    /// [Layout::BitReversed], computed in as many hashes, and of `O(log n)` with the other
+    /// layouts.
+    pub fn consistency(&self, from: usize) -> Option<Consistency> {
+        if from > self.data.len() {
             return None;
         }
-        let mut current_node = &self.tree;
-        while let Tree::Node { left, right } = current_node {
-            if nth & 0x1 > 0 {
-                current_node = right.as_ref();
-            } else {
-                current_node = left.as_ref();
+        let kind = match &self.tree {
+            Tree::BitReversed(_) if from == 0 => ConsistencyKind::Empty {
+                to: self.data.len(),
+            },
+            Tree::BitReversed(tree) => {
+                // the proofs of the pushes, replayed on the tree as it was.
+                let mut past = tree.past(from);
+                let steps = (from..self.data.len()).map(|nth| {
+                    past.push();
+                    Step {
+                        hash: tree.leaves()[nth],
+                        proof: Proof {
+                            nth,
+                            layout: Layout::BitReversed,
+                            size: Some(nth + 1),
+                            hashes: past.path(nth),
+                        },
+                    }
+                });
+                ConsistencyKind::Steps {
+                    steps: steps.collect(),
+                }
             }
-            nth >>= 1;
-        }
-        Some(current_node.hash())
+            Tree::Rfc6962 { subtrees, .. } => ConsistencyKind::Rfc6962 {
+                to: self.data.len(),
+                hashes: if from == 0 {
+                    vec![]
+                } else {
+                    subtrees.consistency(from, self.data.len())
+                },
+            },
+            Tree::Mmr(mmr) => ConsistencyKind::Mmr {
+                to: self.data.len(),
+                peaks: mmr.peaks(from),
+                hashes: mmr.ancestry(from, self.data.len()),
+            },
+        };
+        Some(Consistency { from, kind })
     }
+
+    pub fn get_hash(&self, nth: usize) -> Option<blake3::Hash> {
+        self.tree.leaves().get(nth).copied()
+    }
+}
+
+impl<D: Clone> HMap<D> {
     /// Get an element by index. the current API returns it with it's proof but it may change
     /// later.
     pub fn get(&self, nth: usize) -> Option<D> {
@@ -237,27 +735,43 @@ impl<D: Hash + Clone> HMap<D> {
     }
 }
 
+impl Default for Tree {
+    fn default() -> Self {
+        Tree::new(Layout::default(), vec![])
+    }
+}
+
 impl Tree {
-    pub fn merge(self, with: Self) -> Self {
-        if matches!(self, Tree::Empty) {
-            return with;
-        } else if matches!(with, Tree::Empty) {
-            return self;
+    /// The tree of the `leaves` placed with `layout`, its nodes being built from the bottom.
+    fn new(layout: Layout, leaves: Vec<blake3::Hash>) -> Self {
+        match layout {
+            Layout::BitReversed => Tree::BitReversed(bit_reversed::BitReversed::new(leaves)),
+            Layout::Rfc6962 => Tree::Rfc6962 {
+                subtrees: rfc6962::Subtrees::from_leaves(parallel::map(
+                    &leaves,
+                    parallel::MIN_LEN,
+                    |h| rfc6962::leaf_hash(h.as_bytes()),
+                )),
+                leaves,
+            },
+            Layout::Mmr => Tree::Mmr(mmr::Mmr::from_leaves(leaves)),
         }
-        Self::Node {
-            left: Box::new(self),
-            right: Box::new(with),
+    }
+
+    fn layout(&self) -> Layout {
+        match self {
+            Tree::BitReversed(_) => Layout::BitReversed,
+            Tree::Rfc6962 { .. } => Layout::Rfc6962,
+            Tree::Mmr(_) => Layout::Mmr,
         }
     }
 
-    fn hash(&self) -> blake3::Hash {
+    /// The hashes of the elements, in insertion order.
+    fn leaves(&self) -> &[blake3::Hash] {
         match self {
-            Self::Node { left, right } => blake3::Hasher::new()
-                .update(left.hash().as_bytes())
-                .update(right.hash().as_bytes())
-                .finalize(),
-            Self::Leaf { hash } => *hash,
-            _ => panic!(),
+            Tree::BitReversed(tree) => tree.leaves(),
+            Tree::Rfc6962 { leaves, .. } => leaves,
+            Tree::Mmr(mmr) => mmr.leaves(),
         }
     }
 }
@@ -266,131 +780,188 @@ impl Tree {
 mod tests {
     use super::*;
 
-    #[test]
-    // A merge should keep the order of the ops.
-    // Merge of empty trees is empty.
-    fn sim_merge() {
-        // both empty
-        let left = Tree::Empty;
-        let right = Tree::Empty;
-        assert_eq!(left.merge(right), Tree::Empty,);
-        // both leafs
-        let left = Tree::Leaf {
-            hash: blake3::hash(&[0u8]),
-        };
-        let right = Tree::Leaf {
-            hash: blake3::hash(&[1u8]),
-        };
-        assert_eq!(
-            left.clone().merge(right.clone()),
-            Tree::Node {
-                left: Box::new(left),
-                right: Box::new(right),
+    fn hashes(n: u8) -> Vec<blake3::Hash> {
+        (0..n).map(|i| blake3::hash(&[i])).collect()
+    }
+
+    // The nested tree older versions wrote for the `hashes` of a bit reversed store.
+    fn nested_bit_reversed(
+        hashes: &[blake3::Hash],
+        offset: usize,
+        stride: usize,
+    ) -> serde_json::Value {
+        if offset >= hashes.len() {
+            serde_json::json!("Empty")
+        } else if offset + stride >= hashes.len() {
+            serde_json::json!({ "Leaf": { "hash": hashes[offset].to_hex().as_str() } })
+        } else {
+            serde_json::json!({ "Node": {
+                "left": nested_bit_reversed(hashes, offset, stride * 2),
+                "right": nested_bit_reversed(hashes, offset + stride, stride * 2),
+            } })
+        }
+    }
+
+    // The nested tree older versions wrote for the `hashes` of a RFC 6962 store.
+    fn nested_left_filled(hashes: &[blake3::Hash]) -> serde_json::Value {
+        match hashes {
+            [] => serde_json::json!("Empty"),
+            [hash] => serde_json::json!({ "Leaf": { "hash": hash.to_hex().as_str() } }),
+            _ => {
+                let (left, right) = hashes.split_at(rfc6962::split(hashes.len()));
+                serde_json::json!({ "Node": {
+                    "left": nested_left_filled(left),
+                    "right": nested_left_filled(right),
+                } })
             }
-        );
+        }
     }
 
-    #[test]
-    // When merging anything with an empty tree, it result to this thing.
-    // The merge is associative in this case.
-    fn identity_merge() {
-        // left is empty
-        let left = Tree::Empty;
-        let right = Tree::Leaf {
-            hash: blake3::hash(&[1u8]),
-        };
-        assert_eq!(left.merge(right.clone()), right,);
-        // right is empty;
-        let left = Tree::Leaf {
-            hash: blake3::hash(&[0u8]),
-        };
-        let right = Tree::Empty;
-        assert_eq!(left.clone().merge(right), left,);
+    // A bit reversed store as older versions wrote it.
+    fn nested_store(n: u8) -> HMap<u8> {
+        let tree = nested_bit_reversed(&hashes(n), 0, 1);
+        serde_json::from_value(
+            serde_json::json!({ "data": (0..n).collect::<Vec<_>>(), "tree": tree }),
+        )
+        .unwrap()
     }
 
     #[test]
-    /// Merge is a simple op, it does not try to reshape the tree.
-    fn deep_merge() {
-        let left = Tree::Leaf {
-            hash: blake3::hash(&[0u8]),
-        };
-        let right = Tree::Node {
-            left: Box::new(Tree::Leaf {
-                hash: blake3::hash(&[1u8]),
-            }),
-            right: Box::new(Tree::Leaf {
-                hash: blake3::hash(&[2u8]),
-            }),
-        };
-        assert_eq!(
-            left.clone().merge(right.clone()),
-            Tree::Node {
-                left: Box::new(left),
-                right: Box::new(right),
-            }
-        );
+    #[should_panic(expected = "empty store")]
+    fn empty_root() {
+        HMap::<()>::with_layout(Layout::Rfc6962).root();
     }
 
     #[test]
     fn hash() {
-        let left = Tree::Leaf {
-            hash: blake3::hash(&[0u8]),
-        };
-        let right = Tree::Leaf {
-            hash: blake3::hash(&[1u8]),
+        let root = |hashes: Vec<blake3::Hash>| {
+            HMap::from_leaves(Layout::BitReversed, hashes.into_iter().map(|h| (h, ()))).root()
         };
+        let (left, right) = (blake3::hash(&[0u8]), blake3::hash(&[1u8]));
 
         // a leaf hash is it's contained hash.
-        assert_eq!(right.clone().hash(), blake3::hash(&[1u8]),);
+        assert_eq!(root(vec![right]), right);
 
         // resist over extention with 0.
-        assert_ne!(
-            left.clone().merge(right.clone()).hash(),
-            right.clone().hash(),
-        );
-        assert_ne!(
-            left.clone().merge(right.clone()).hash(),
-            left.clone().hash(),
-        );
+        assert_ne!(root(vec![left, right]), right);
+        assert_ne!(root(vec![left, right]), left);
 
         // A tree hash differ if elems are not in the same order.
-        assert_ne!(
-            left.clone().merge(right.clone()).hash(),
-            right.merge(left).hash(),
+        assert_ne!(root(vec![left, right]), root(vec![right, left]));
+    }
+
+    #[test]
+    // The nested trees of older versions are read back as the flat ones of the same leaves.
+    fn nested() {
+        for n in 0..40 {
+            let mut stores: Vec<HMap<u8>> = vec![nested_store(n)];
+            let rfc = nested_left_filled(&hashes(n));
+            stores.push(
+                serde_json::from_value(serde_json::json!({
+                    "data": (0..n).collect::<Vec<_>>(),
+                    "tree": rfc,
+                    "layout": "rfc6962",
+                }))
+                .unwrap(),
+            );
+            let mmr = serde_json::json!({
+                "data": (0..n).collect::<Vec<_>>(),
+                "tree": "Empty",
+                "layout": "mmr",
+                "mmr": { "leaves": hashes(n).iter().map(|h| h.to_hex().to_string()).collect::<Vec<_>>() },
+            });
+            stores.push(serde_json::from_value(mmr).unwrap());
+            for store in stores {
+                let mut pushed = HMap::with_layout(store.layout());
+                for i in 0..n {
+                    pushed.push(blake3::hash(&[i]), i);
+                }
+                assert_eq!(store.tree, pushed.tree);
+                assert_eq!(
+                    serde_json::to_value(&store).unwrap(),
+                    serde_json::to_value(&pushed).unwrap()
+                );
+                for i in 0..n as usize {
+                    assert_eq!(store.proof(i), pushed.proof(i));
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Hashes are hex strings, the legacy arrays of bytes are still read.
+    fn serde() {
+        let hash = blake3::hash(&[0u8]);
+        let store = HMap::from_leaves(Layout::BitReversed, [(hash, 0u8)]);
+        let json = serde_json::to_value(&store).unwrap();
+        // the tree is stored flat, as its leaves.
+        assert_eq!(
+            json["tree"],
+            serde_json::json!({ "leaves": [hash.to_hex().as_str()] })
         );
+        assert_eq!(
+            serde_json::from_value::<HMap<u8>>(json).unwrap().tree,
+            store.tree
+        );
+        let legacy =
+            serde_json::json!({ "data": [0], "tree": { "Leaf": { "hash": hash.as_bytes() } } });
+        assert_eq!(
+            serde_json::from_value::<HMap<u8>>(legacy).unwrap().tree,
+            store.tree
+        );
+
+        let proof = Proof {
+            nth: 1,
+            layout: Layout::BitReversed,
+            size: None,
+            hashes: vec![hash],
+        };
+        let json = serde_json::to_value(&proof).unwrap();
+        assert_eq!(json["hashes"][0], hash.to_hex().as_str());
+        assert_eq!(serde_json::from_value::<Proof>(json).unwrap(), proof);
+        let legacy = serde_json::json!({ "nth": 1, "hashes": [hash.as_bytes()] });
+        assert_eq!(serde_json::from_value::<Proof>(legacy).unwrap(), proof);
+
+        let wrong = serde_json::json!({ "data": [0], "tree": { "Leaf": { "hash": "00" } } });
+        assert!(serde_json::from_value::<HMap<u8>>(wrong).is_err());
+    }
+
+    #[test]
+    // The proof of an element at a past size is the one returned when it was pushed.
+    fn proof_at() {
+        let mut store = HMap::new();
+        let mut proofs = vec![];
+        for i in 0u8..10u8 {
+            proofs.push(store.push(blake3::hash(&[i]), i));
+        }
+        for (nth, proof) in proofs.into_iter().enumerate() {
+            assert_eq!(store.proof_at(nth, nth + 1), Some(proof));
+            assert_eq!(store.proof_at(nth, 10), store.proof(nth));
+        }
+        assert_eq!(store.proof_at(3, 3), None);
+        assert_eq!(store.proof_at(3, 11), None);
     }
 
     #[test]
     fn proof() {
-        let store = HMap {
-            data: vec![0],
-            tree: Tree::Leaf {
-                hash: blake3::hash(&[0u8]),
-            },
-        };
+        let store = nested_store(1);
         assert_eq!(
             store.proof(0),
             Some(Proof {
                 nth: 0,
+                layout: Layout::BitReversed,
+                size: Some(1),
                 hashes: vec![]
             })
         );
 
-        let store = HMap {
-            data: vec![0u8, 1u8],
-            tree: Tree::Node {
-                left: Box::new(Tree::Leaf {
-                    hash: blake3::hash(&[0u8]),
-                }),
-                right: Box::new(Tree::Leaf {
-                    hash: blake3::hash(&[1u8]),
-                }),
-            },
-        };
+        let store = nested_store(2);
         assert_eq!(
             store.proof(0),
             Some(Proof {
                 nth: 0,
+                layout: Layout::BitReversed,
+                size: Some(2),
                 hashes: vec![blake3::hash(&[1u8])],
             })
         );
@@ -398,30 +969,19 @@ mod tests {
             store.proof(1),
             Some(Proof {
                 nth: 1,
+                layout: Layout::BitReversed,
+                size: Some(2),
                 hashes: vec![blake3::hash(&[0u8])],
             })
         );
 
-        let store = HMap {
-            data: vec![0u8, 1u8, 2u8],
-            tree: Tree::Node {
-                left: Box::new(Tree::Node {
-                    left: Box::new(Tree::Leaf {
-                        hash: blake3::hash(&[0u8]),
-                    }),
-                    right: Box::new(Tree::Leaf {
-                        hash: blake3::hash(&[2u8]),
-                    }),
-                }),
-                right: Box::new(Tree::Leaf {
-                    hash: blake3::hash(&[1u8]),
-                }),
-            },
-        };
+        let store = nested_store(3);
         assert_eq!(
             store.proof(0),
             Some(Proof {
                 nth: 0,
+                layout: Layout::BitReversed,
+                size: Some(3),
                 hashes: vec![blake3::hash(&[1u8]), blake3::hash(&[2u8]),],
             })
         );
@@ -429,6 +989,8 @@ mod tests {
             store.proof(1),
             Some(Proof {
                 nth: 1,
+                layout: Layout::BitReversed,
+                size: Some(3),
                 hashes: vec![blake3::Hasher::new()
                     .update(blake3::hash(&[0u8]).as_bytes())
                     .update(blake3::hash(&[2u8]).as_bytes())
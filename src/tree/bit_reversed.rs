@@ -0,0 +1,202 @@
+//! The tree of the [Layout::BitReversed](super::Layout::BitReversed).
+//!
+//! The `nth` element is found by following the bits of `nth` from the least significant one, so
+//! the node of depth `d` holding the element `nth` holds the elements `nth % 2^d + k * 2^d`.
+use super::{hash, parallel};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The tree, stored flat: the node of depth `d` holding the elements `o + k * 2^d` is
+/// `levels[d][o]`, the nodes below the last level being the leaves.
+///
+/// The nodes of a past size are rehashed on demand, see [Past].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BitReversed {
+    /// The hashes of the elements, in insertion order.
+    leaves: Vec<blake3::Hash>,
+    levels: Vec<Vec<blake3::Hash>>,
+}
+
+impl BitReversed {
+    /// The tree of the `leaves`, its levels being built from the bottom in linear time.
+    pub fn new(leaves: Vec<blake3::Hash>) -> Self {
+        let depth = (usize::BITS - leaves.len().saturating_sub(1).leading_zeros()) as usize;
+        let mut tree = BitReversed {
+            leaves,
+            levels: vec![vec![]; depth],
+        };
+        for d in (0..depth).rev() {
+            let len = tree.leaves.len();
+            let level = parallel::indices(1 << d, |o| combine(len, d, o, |d, o| tree.node(d, o)));
+            tree.levels[d] = level;
+        }
+        tree
+    }
+
+    pub fn leaves(&self) -> &[blake3::Hash] {
+        &self.leaves
+    }
+
+    /// The root, `None` when empty.
+    pub fn root(&self) -> Option<blake3::Hash> {
+        (!self.leaves.is_empty()).then(|| self.node(0, 0))
+    }
+
+    /// The node of depth `d` holding the elements `o + k * 2^d`.
+    fn node(&self, d: usize, o: usize) -> blake3::Hash {
+        match self.levels.get(d) {
+            Some(level) => level[o],
+            None => self.leaves[o],
+        }
+    }
+
+    /// Push a leaf, rehashing the nodes holding it.
+    pub fn push(&mut self, hash: blake3::Hash) {
+        let nth = self.leaves.len();
+        let depth = self.levels.len();
+        // the tree gets deeper: the leaves of its last level become nodes.
+        if nth > 0 && nth >= 1 << depth {
+            self.levels.push(self.leaves[..1 << depth].to_vec());
+        }
+        self.leaves.push(hash);
+        for d in (0..self.levels.len()).rev() {
+            let o = nth & ((1 << d) - 1);
+            self.levels[d][o] = combine(nth + 1, d, o, |d, o| self.node(d, o));
+        }
+    }
+
+    /// The tree as it was with `size` leaves.
+    pub fn past(&self, size: usize) -> Past<'_> {
+        let mut past = Past {
+            tree: self,
+            base: size,
+            size,
+            nodes: vec![vec![]; self.levels.len()],
+        };
+        let pushed = self.leaves.len() - size;
+        for d in (0..self.levels.len()).rev() {
+            let level = parallel::indices(pushed.min(1 << d), |i| {
+                let o = (size + i) & ((1 << d) - 1);
+                if o < size {
+                    combine(size, d, o, |d, o| past.node(d, o))
+                } else {
+                    // not in the tree yet, set when its first element is pushed.
+                    self.node(d, o)
+                }
+            });
+            past.nodes[d] = level;
+        }
+        past
+    }
+}
+
+/// The node of depth `d` holding the elements `o + k * 2^d` of a tree of `size` leaves, hashed
+/// from its children given by `node`. It's its left child when it holds a single element.
+fn combine(
+    size: usize,
+    d: usize,
+    o: usize,
+    node: impl Fn(usize, usize) -> blake3::Hash,
+) -> blake3::Hash {
+    let right = o + (1 << d);
+    if right < size {
+        hash::node_hash(&node(d + 1, o), &node(d + 1, right))
+    } else {
+        node(d + 1, o)
+    }
+}
+
+/// The tree as it was with `size` leaves, `k` leaves having been pushed since.
+///
+/// A node holding none of these `k` elements is the current one. The others are rehashed from
+/// the bottom, `min(2^d, k)` of them at the depth `d`: `nodes[d][i]` holds the elements
+/// `(base + i) % 2^d + j * 2^d`. That's `O(k log n)` hashes, the current nodes being read for
+/// the rest of a proof.
+pub struct Past<'a> {
+    tree: &'a BitReversed,
+    /// The size the nodes were rehashed for, [Past::push] growing `size` from it.
+    base: usize,
+    size: usize,
+    nodes: Vec<Vec<blake3::Hash>>,
+}
+
+impl Past<'_> {
+    /// The node of depth `d` holding the elements `o + k * 2^d`, for `o < size`.
+    fn node(&self, d: usize, o: usize) -> blake3::Hash {
+        let i = o.wrapping_sub(self.base) & ((1 << d) - 1);
+        match self.nodes.get(d).and_then(|level| level.get(i)) {
+            Some(node) => *node,
+            None => self.tree.node(d, o),
+        }
+    }
+
+    /// Push the next leaf of the tree, rehashing the nodes holding it.
+    pub fn push(&mut self) {
+        let nth = self.size;
+        self.size += 1;
+        for d in (0..self.nodes.len()).rev() {
+            let o = nth & ((1 << d) - 1);
+            let node = combine(self.size, d, o, |d, o| self.node(d, o));
+            let i = o.wrapping_sub(self.base) & ((1 << d) - 1);
+            self.nodes[d][i] = node;
+        }
+    }
+
+    /// The proof hashes of the `nth` leaf, from the root down.
+    pub fn path(&self, nth: usize) -> Vec<blake3::Hash> {
+        let mut hashes = vec![];
+        let mut d = 0;
+        // while the node of depth `d` holding `nth` holds other elements.
+        while (nth & ((1 << d) - 1)) + (1 << d) < self.size {
+            let brother = (nth & ((1 << (d + 1)) - 1)) ^ (1 << d);
+            hashes.push(self.node(d + 1, brother));
+            d += 1;
+        }
+        hashes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hashes(n: u8) -> Vec<blake3::Hash> {
+        (0..n).map(|i| blake3::hash(&[i])).collect()
+    }
+
+    #[test]
+    // Pushing a leaf updates the levels as building them from all the leaves.
+    fn push() {
+        let mut tree = BitReversed::default();
+        for n in 0..70 {
+            assert_eq!(tree, BitReversed::new(hashes(n)));
+            tree.push(blake3::hash(&[n]));
+        }
+        assert_eq!(tree.levels.len(), 7);
+    }
+
+    #[test]
+    // The nodes rehashed for the past sizes are the ones of the stores of these sizes, and
+    // pushing to them replays the pushes of the store.
+    fn past_nodes() {
+        let tree = BitReversed::new(hashes(70));
+        for size in 1..70u8 {
+            let past = BitReversed::new(hashes(size));
+            let view = tree.past(size as usize);
+            for nth in 0..size as usize {
+                assert_eq!(view.path(nth), past.past(size as usize).path(nth));
+            }
+            assert_eq!(view.node(0, 0), past.root().unwrap());
+        }
+        let mut view = tree.past(5);
+        for size in 6..=70u8 {
+            view.push();
+            let past = BitReversed::new(hashes(size));
+            assert_eq!(view.node(0, 0), past.root().unwrap());
+            assert_eq!(
+                view.path(size as usize - 1),
+                past.past(size as usize).path(size as usize - 1)
+            );
+        }
+    }
+}
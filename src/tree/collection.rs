@@ -0,0 +1,191 @@
+//! Collection API of a [HMap]: iteration, indexing and building from hashed elements.
+use super::{HMap, Layout, Proof};
+use core::iter::FusedIterator;
+use core::ops::{Index, Range};
+
+/// Iterator over the elements of a [HMap] with their index and hash, returned by [HMap::iter].
+pub struct Iter<'a, D> {
+    store: &'a HMap<D>,
+    range: Range<usize>,
+}
+
+/// Iterator over the elements of a [HMap] with their index, hash and proof, returned by
+/// [HMap::iter_proofs].
+pub struct IterProofs<'a, D> {
+    iter: Iter<'a, D>,
+}
+
+impl<D> HMap<D> {
+    /// Borrow the `nth` element.
+    pub fn get_ref(&self, nth: usize) -> Option<&D> {
+        self.data.get(nth)
+    }
+
+    /// The elements, in insertion order.
+    pub fn as_slice(&self) -> &[D] {
+        &self.data
+    }
+
+    /// Iterate over the elements in insertion order, as `(index, element, hash)`.
+    pub fn iter(&self) -> Iter<'_, D> {
+        Iter {
+            store: self,
+            range: 0..self.data.len(),
+        }
+    }
+
+    /// Iterate over the elements in insertion order, as `(index, element, hash, proof)`, the
+    /// proofs being against the current root.
+    ///
+    /// Each proof is read from the nodes of the tree in O(log n), with any layout.
+    pub fn iter_proofs(&self) -> IterProofs<'_, D> {
+        IterProofs { iter: self.iter() }
+    }
+}
+
+impl<'a, D> Iter<'a, D> {
+    fn item(&self, nth: usize) -> (usize, &'a D, blake3::Hash) {
+        (
+            nth,
+            &self.store.data[nth],
+            self.store.get_hash(nth).unwrap(),
+        )
+    }
+}
+
+impl<'a, D> Iterator for Iter<'a, D> {
+    type Item = (usize, &'a D, blake3::Hash);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.range.next().map(|nth| self.item(nth))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+impl<'a, D> DoubleEndedIterator for Iter<'a, D> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.range.next_back().map(|nth| self.item(nth))
+    }
+}
+
+impl<'a, D> ExactSizeIterator for Iter<'a, D> {}
+
+impl<'a, D> FusedIterator for Iter<'a, D> {}
+
+impl<'a, D> Iterator for IterProofs<'a, D> {
+    type Item = (usize, &'a D, blake3::Hash, Proof);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (nth, data, hash) = self.iter.next()?;
+        Some((nth, data, hash, self.iter.store.proof(nth).unwrap()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, D> DoubleEndedIterator for IterProofs<'a, D> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (nth, data, hash) = self.iter.next_back()?;
+        Some((nth, data, hash, self.iter.store.proof(nth).unwrap()))
+    }
+}
+
+impl<'a, D> ExactSizeIterator for IterProofs<'a, D> {}
+
+impl<'a, D> FusedIterator for IterProofs<'a, D> {}
+
+impl<'a, D> IntoIterator for &'a HMap<D> {
+    type Item = (usize, &'a D, blake3::Hash);
+    type IntoIter = Iter<'a, D>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Panics if there is no `nth` element, use [HMap::get_ref] otherwise.
+impl<D> Index<usize> for HMap<D> {
+    type Output = D;
+
+    fn index(&self, nth: usize) -> &D {
+        &self.data[nth]
+    }
+}
+
+/// Build a store of the default layout from the `(hash, element)` pairs, see
+/// [HMap::from_leaves].
+impl<D> FromIterator<(blake3::Hash, D)> for HMap<D> {
+    fn from_iter<I: IntoIterator<Item = (blake3::Hash, D)>>(iter: I) -> Self {
+        HMap::from_leaves(Layout::BitReversed, iter)
+    }
+}
+
+/// Push the `(hash, element)` pairs, dropping their proofs.
+impl<D> Extend<(blake3::Hash, D)> for HMap<D> {
+    fn extend<I: IntoIterator<Item = (blake3::Hash, D)>>(&mut self, iter: I) {
+        for (hash, data) in iter {
+            self.push(hash, data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(n: u8) -> impl Iterator<Item = (blake3::Hash, u8)> {
+        (0..n).map(|i| (blake3::hash(&[i]), i))
+    }
+
+    #[test]
+    fn iter() {
+        for layout in [Layout::BitReversed, Layout::Rfc6962, Layout::Mmr] {
+            let mut store = HMap::with_layout(layout);
+            store.extend(pairs(11));
+            let root = store.root();
+            assert_eq!(store.iter().len(), 11);
+            for (nth, data, hash) in &store {
+                assert_eq!((*data, hash), (nth as u8, blake3::hash(&[nth as u8])));
+            }
+            for (nth, data, hash, proof) in store.iter_proofs().rev() {
+                assert_eq!(store[nth], *data);
+                assert!(proof.verify(hash, nth, 11, root, layout).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn collect() {
+        let store: HMap<u8> = pairs(7).collect();
+        let mut pushed = HMap::new();
+        for (hash, data) in pairs(7) {
+            pushed.push(hash, data);
+        }
+        assert_eq!(store.root(), pushed.root());
+        assert_eq!(store.as_slice(), &[0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(store.get_ref(3), Some(&3));
+        assert_eq!(store.get_ref(7), None);
+    }
+
+    #[test]
+    // The elements are borrowed, they needn't be `Clone`.
+    fn not_clone() {
+        #[derive(Debug, PartialEq)]
+        struct Element(u8);
+
+        let store: HMap<Element> = pairs(5).map(|(hash, i)| (hash, Element(i))).collect();
+        assert_eq!(store.get_ref(2), Some(&Element(2)));
+        assert_eq!(store.iter().map(|(_, data, _)| data.0).sum::<u8>(), 10);
+        let root = store.root();
+        for (nth, _, hash, proof) in store.iter_proofs() {
+            assert!(proof
+                .verify(hash, nth, 5, root, Layout::BitReversed)
+                .is_ok());
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! Compact binary encoding of a [Proof].
+//!
+//! The wire format is stable: a proof encoded by a version of this crate is decoded by every
+//! later one. It's made of, in order:
+//!
+//! | field   | encoding                                                       |
+//! |---------|----------------------------------------------------------------|
+//! | version | one byte, `1`                                                  |
+//! | layout  | one byte: `0` bit reversed, `1` RFC 6962, `2` MMR              |
+//! | nth     | unsigned LEB128                                                |
+//! | size    | unsigned LEB128, `0` when the proof has no size                |
+//! | count   | unsigned LEB128, the number of hashes                          |
+//! | hashes  | `count` times 32 bytes, in the order of the proof              |
+//!
+//! Numbers are in their shortest LEB128 form, so a proof has a single encoding.
+//!
+//! A new layout gets a new layout byte; any other change of the format bumps the version.
+//!
+//! The bytes can also be carried as text, hex encoded or in standard base64 (with padding), for
+//! instance in JSON with [proof_hex] and [proof_base64].
+use super::{Layout, Proof};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use base64::Engine;
+use core::fmt;
+
+/// The version of the encoding written by [Proof::to_bytes].
+const VERSION: u8 = 1;
+
+/// Error returned when decoding a [Proof].
+#[derive(Debug, PartialEq)]
+pub enum DecodeError {
+    /// The input ends in the middle of a field.
+    Truncated,
+    /// The version byte is not a known version.
+    Version(u8),
+    /// The layout byte is not a known layout.
+    Layout(u8),
+    /// A number doesn't fit in a `usize`.
+    Overflow,
+    /// A number is encoded on more bytes than needed.
+    NonCanonical,
+    /// Bytes are left after the proof.
+    TrailingBytes,
+    /// The text form is not valid hex or base64.
+    Text,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Truncated => write!(f, "truncated proof"),
+            DecodeError::Version(v) => write!(f, "unsupported proof encoding version {}", v),
+            DecodeError::Layout(l) => write!(f, "unknown layout {}", l),
+            DecodeError::Overflow => write!(f, "number too large"),
+            DecodeError::NonCanonical => write!(f, "number not in its shortest form"),
+            DecodeError::TrailingBytes => write!(f, "trailing bytes after the proof"),
+            DecodeError::Text => write!(f, "invalid hex or base64"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+fn write_varint(bytes: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        bytes.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    bytes.push(n as u8);
+}
+
+/// Reads the bytes of a proof from the front.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        if self.0.len() < n {
+            return Err(DecodeError::Truncated);
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<usize, DecodeError> {
+        let mut n: usize = 0;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = self.byte()?;
+            let bits = (byte & 0x7f) as usize;
+            if bits << shift >> shift != bits {
+                return Err(DecodeError::Overflow);
+            }
+            n |= bits << shift;
+            if byte & 0x80 == 0 {
+                // a last byte of 0 only adds a useless group of zeros.
+                if byte == 0 && shift > 0 {
+                    return Err(DecodeError::NonCanonical);
+                }
+                return Ok(n);
+            }
+        }
+        Err(DecodeError::Overflow)
+    }
+}
+
+impl Proof {
+    /// Encode this proof in the compact binary format described in the module documentation.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 + 32 * self.hashes.len());
+        bytes.push(VERSION);
+        bytes.push(match self.layout {
+            Layout::BitReversed => 0,
+            Layout::Rfc6962 => 1,
+            Layout::Mmr => 2,
+        });
+        write_varint(&mut bytes, self.nth);
+        write_varint(&mut bytes, self.size.unwrap_or(0));
+        write_varint(&mut bytes, self.hashes.len());
+        for hash in &self.hashes {
+            bytes.extend_from_slice(hash.as_bytes());
+        }
+        bytes
+    }
+
+    /// Decode a proof encoded by [Proof::to_bytes].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader(bytes);
+        let version = reader.byte()?;
+        if version != VERSION {
+            return Err(DecodeError::Version(version));
+        }
+        let layout = match reader.byte()? {
+            0 => Layout::BitReversed,
+            1 => Layout::Rfc6962,
+            2 => Layout::Mmr,
+            l => return Err(DecodeError::Layout(l)),
+        };
+        let nth = reader.varint()?;
+        let size = Some(reader.varint()?).filter(|size| *size > 0);
+        let count = reader.varint()?;
+        // don't trust `count` to allocate.
+        if count > reader.0.len() / 32 {
+            return Err(DecodeError::Truncated);
+        }
+        let hashes = (0..count)
+            .map(|_| {
+                let bytes: [u8; 32] = reader.take(32)?.try_into().unwrap();
+                Ok(blake3::Hash::from_bytes(bytes))
+            })
+            .collect::<Result<_, DecodeError>>()?;
+        if !reader.0.is_empty() {
+            return Err(DecodeError::TrailingBytes);
+        }
+        Ok(Proof {
+            nth,
+            layout,
+            size,
+            hashes,
+        })
+    }
+
+    /// The binary encoding of this proof, hex encoded.
+    pub fn to_hex(&self) -> String {
+        self.to_bytes()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Decode a proof encoded by [Proof::to_hex].
+    pub fn from_hex(hex: &str) -> Result<Self, DecodeError> {
+        if hex.len() % 2 == 1 || !hex.is_ascii() {
+            return Err(DecodeError::Text);
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| DecodeError::Text))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// The binary encoding of this proof, in standard base64.
+    pub fn to_base64(&self) -> String {
+        base64::engine::general_purpose::STANDARD.encode(self.to_bytes())
+    }
+
+    /// Decode a proof encoded by [Proof::to_base64].
+    pub fn from_base64(base64: &str) -> Result<Self, DecodeError> {
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(base64)
+            .map_err(|_| DecodeError::Text)?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// Serialize a [Proof] as the hex string of its binary encoding, with
+/// `#[serde(with = "proof_hex")]`.
+pub mod proof_hex {
+    use super::{Proof, String};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(proof: &Proof, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&proof.to_hex())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Proof, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        Proof::from_hex(&hex).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Serialize a [Proof] as the base64 string of its binary encoding, with
+/// `#[serde(with = "proof_base64")]`.
+pub mod proof_base64 {
+    use super::{Proof, String};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(proof: &Proof, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&proof.to_base64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Proof, D::Error> {
+        let base64 = String::deserialize(deserializer)?;
+        Proof::from_base64(&base64).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn varint() {
+        for n in [0, 1, 127, 128, 300, 1 << 35, usize::MAX] {
+            let mut bytes = vec![];
+            write_varint(&mut bytes, n);
+            let mut reader = Reader(&bytes);
+            assert_eq!(reader.varint(), Ok(n));
+            assert!(reader.0.is_empty());
+        }
+        // 11 bytes of ones overflow.
+        assert_eq!(Reader(&[0xff; 11]).varint(), Err(DecodeError::Overflow));
+        assert_eq!(Reader(&[0x80]).varint(), Err(DecodeError::Truncated));
+        // 0 and 1 padded with a group of zeros.
+        assert_eq!(
+            Reader(&[0x80, 0x00]).varint(),
+            Err(DecodeError::NonCanonical)
+        );
+        assert_eq!(
+            Reader(&[0x81, 0x80, 0x00]).varint(),
+            Err(DecodeError::NonCanonical)
+        );
+    }
+
+    #[test]
+    fn layout() {
+        let proof = Proof {
+            nth: 300,
+            layout: Layout::Mmr,
+            size: Some(301),
+            hashes: vec![blake3::hash(b"a"), blake3::hash(b"b")],
+        };
+        let bytes = proof.to_bytes();
+        assert_eq!(bytes[..7], [1, 2, 0xac, 0x02, 0xad, 0x02, 2]);
+        assert_eq!(bytes.len(), 7 + 64);
+        assert_eq!(Proof::from_bytes(&bytes), Ok(proof));
+    }
+
+    #[test]
+    fn errors() {
+        let proof = Proof {
+            nth: 3,
+            layout: Layout::BitReversed,
+            size: None,
+            hashes: vec![blake3::hash(b"a")],
+        };
+        let bytes = proof.to_bytes();
+        assert_eq!(Proof::from_bytes(&[]), Err(DecodeError::Truncated));
+        assert_eq!(
+            Proof::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(DecodeError::Truncated)
+        );
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert_eq!(Proof::from_bytes(&longer), Err(DecodeError::TrailingBytes));
+        let mut version = bytes.clone();
+        version[0] = 2;
+        assert_eq!(Proof::from_bytes(&version), Err(DecodeError::Version(2)));
+        let mut layout = bytes;
+        layout[1] = 9;
+        assert_eq!(Proof::from_bytes(&layout), Err(DecodeError::Layout(9)));
+        assert_eq!(Proof::from_hex("0"), Err(DecodeError::Text));
+        assert_eq!(Proof::from_base64("!"), Err(DecodeError::Text));
+    }
+}
@@ -0,0 +1,10 @@
+//! The BLAKE3 node hash of the [Layout::BitReversed](super::Layout::BitReversed) and
+//! [Layout::Mmr](super::Layout::Mmr) trees, and of the sparse map.
+
+/// Hash of a node: `BLAKE3(left || right)`.
+pub fn node_hash(left: &blake3::Hash, right: &blake3::Hash) -> blake3::Hash {
+    blake3::Hasher::new()
+        .update(left.as_bytes())
+        .update(right.as_bytes())
+        .finalize()
+}
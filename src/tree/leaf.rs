@@ -0,0 +1,108 @@
+//! Elements hashed by the store itself.
+use super::HMap;
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// An element with a canonical byte encoding, so a store can hash it itself with
+/// [HMap::push_item] and check it back with [HMap::verify_item].
+///
+/// The leaf hash is the BLAKE3 hash of the bytes, as the one callers of [HMap::push] pass for
+/// files, so both kinds of elements can share a store.
+pub trait MerkleLeaf {
+    /// The canonical encoding of the element: equal elements must give the same bytes.
+    fn leaf_bytes(&self) -> Cow<'_, [u8]>;
+
+    /// The hash of the leaf of the element.
+    fn leaf_hash(&self) -> blake3::Hash {
+        blake3::hash(&self.leaf_bytes())
+    }
+}
+
+impl MerkleLeaf for [u8] {
+    fn leaf_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl<const N: usize> MerkleLeaf for [u8; N] {
+    fn leaf_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl MerkleLeaf for Vec<u8> {
+    fn leaf_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl MerkleLeaf for str {
+    fn leaf_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self.as_bytes())
+    }
+}
+
+impl MerkleLeaf for String {
+    fn leaf_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self.as_bytes())
+    }
+}
+
+impl<T: MerkleLeaf + ?Sized> MerkleLeaf for &T {
+    fn leaf_bytes(&self) -> Cow<'_, [u8]> {
+        (**self).leaf_bytes()
+    }
+
+    fn leaf_hash(&self) -> blake3::Hash {
+        (**self).leaf_hash()
+    }
+}
+
+impl<D: MerkleLeaf> HMap<D> {
+    /// Push an element hashed by [MerkleLeaf::leaf_hash] and returns it's proof.
+    pub fn push_item(&mut self, data: D) -> super::Proof {
+        let hash = data.leaf_hash();
+        self.push(hash, data)
+    }
+
+    /// Check the `nth` element still hashes to its leaf. `false` when there is no such element.
+    pub fn verify_item(&self, nth: usize) -> bool {
+        match (self.data.get(nth), self.get_hash(nth)) {
+            (Some(data), Some(hash)) => data.leaf_hash() == hash,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    // The leaf hash is the one callers pass to `push` for the same bytes.
+    fn leaf_hash() {
+        let bytes = b"element";
+        assert_eq!(bytes.leaf_hash(), blake3::hash(bytes));
+        assert_eq!(bytes[..].leaf_hash(), blake3::hash(bytes));
+        assert_eq!(bytes.to_vec().leaf_hash(), blake3::hash(bytes));
+        assert_eq!("element".leaf_hash(), blake3::hash(bytes));
+        assert_eq!(String::from("element").leaf_hash(), blake3::hash(bytes));
+    }
+
+    #[test]
+    fn push_item() {
+        let mut items = HMap::new();
+        let mut hashes = HMap::new();
+        for i in 0..10 {
+            let item = format!("item {}", i);
+            assert_eq!(
+                items.push_item(item.clone()),
+                hashes.push(blake3::hash(item.as_bytes()), item)
+            );
+            assert!(items.verify_item(i));
+        }
+        assert_eq!(items.root(), hashes.root());
+        assert!(!items.verify_item(10));
+    }
+}
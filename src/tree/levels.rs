@@ -0,0 +1,61 @@
+//! The perfect subtrees of a list of leaves, the nodes of the trees of the
+//! [Layout::Rfc6962](super::Layout::Rfc6962) and of the [Layout::Mmr](super::Layout::Mmr), which
+//! only differ by their hashes.
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The perfect subtrees level by level: `levels[h][i]` is the root of the subtree of height `h`
+/// holding the leaves `i * 2^h .. (i + 1) * 2^h`, the leaves being `levels[0]`.
+///
+/// A node never changes once built and a push only hashes the subtrees it completes, O(1)
+/// amortized. The nodes are hashed by the `node_hash` function given to build them.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Levels {
+    levels: Vec<Vec<blake3::Hash>>,
+}
+
+impl Levels {
+    /// The subtrees of the given leaves, built level by level.
+    pub fn from_leaves<F>(leaves: Vec<blake3::Hash>, node_hash: F) -> Self
+    where
+        F: Fn(&blake3::Hash, &blake3::Hash) -> blake3::Hash + Sync,
+    {
+        let mut levels = vec![];
+        if !leaves.is_empty() {
+            levels.push(leaves);
+        }
+        while let Some(level) = levels.last().filter(|level| level.len() > 1) {
+            let next = super::parallel::pairs(level, &node_hash);
+            levels.push(next);
+        }
+        Levels { levels }
+    }
+
+    pub fn push<F>(&mut self, leaf: blake3::Hash, node_hash: F)
+    where
+        F: Fn(&blake3::Hash, &blake3::Hash) -> blake3::Hash,
+    {
+        let mut hash = leaf;
+        for height in 0.. {
+            if self.levels.len() == height {
+                self.levels.push(vec![]);
+            }
+            let level = &mut self.levels[height];
+            level.push(hash);
+            let len = level.len();
+            if len % 2 == 1 {
+                break;
+            }
+            hash = node_hash(&level[len - 2], &level[len - 1]);
+        }
+    }
+
+    pub fn leaves(&self) -> &[blake3::Hash] {
+        self.levels.first().map_or(&[], Vec::as_slice)
+    }
+
+    /// Root of the perfect subtree of height `height` starting at the leaf `lo`.
+    pub fn node(&self, lo: usize, height: usize) -> blake3::Hash {
+        self.levels[height][lo >> height]
+    }
+}
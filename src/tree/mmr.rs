@@ -0,0 +1,290 @@
+//! Merkle Mountain Range.
+//!
+//! The elements are the leaves of a list of perfect binary trees, the mountains, one for every
+//! bit set in the number of elements, from the highest to the lowest. Pushing an element only
+//! merges the mountains of the same height, so it's O(1) amortized, and a node never changes once
+//! built: every past version of the range can be read back from the current one.
+//!
+//! The root is the "bagging" of the peaks of the mountains from the right:
+//! `H(p0 || H(p1 || ... H(pk-1 || pk)))`. Nodes are hashed with BLAKE3, as in the
+//! [Layout::BitReversed](super::Layout::BitReversed).
+use super::hash::node_hash;
+use super::levels::Levels;
+use alloc::vec::Vec;
+
+/// The nodes of the range: the root of each mountain is a perfect subtree of the [Levels].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Mmr(Levels);
+
+/// The mountains of a range of `size` leaves, from left to right, as `(first leaf, height)`.
+fn mountains(size: usize) -> impl Iterator<Item = (usize, usize)> {
+    let mut lo = 0;
+    (0..usize::BITS as usize)
+        .rev()
+        .filter(move |h| size >> h & 1 == 1)
+        .map(move |h| {
+            let mountain = (lo, h);
+            lo += 1 << h;
+            mountain
+        })
+}
+
+/// Bag the peaks from the right. `None` when there is no peak.
+pub fn bag(peaks: &[blake3::Hash]) -> Option<blake3::Hash> {
+    peaks
+        .iter()
+        .rev()
+        .copied()
+        .reduce(|bagged, peak| node_hash(&peak, &bagged))
+}
+
+impl Mmr {
+    /// The range of the given leaves, built level by level.
+    pub fn from_leaves(leaves: Vec<blake3::Hash>) -> Self {
+        Mmr(Levels::from_leaves(leaves, node_hash))
+    }
+
+    pub fn push(&mut self, leaf: blake3::Hash) {
+        self.0.push(leaf, node_hash);
+    }
+
+    pub fn leaves(&self) -> &[blake3::Hash] {
+        self.0.leaves()
+    }
+
+    // Root of the perfect subtree of height `height` starting at the leaf `lo`.
+    fn node(&self, lo: usize, height: usize) -> blake3::Hash {
+        self.0.node(lo, height)
+    }
+
+    /// The peaks of the range as it was with `size` leaves, from left to right.
+    pub fn peaks(&self, size: usize) -> Vec<blake3::Hash> {
+        mountains(size).map(|(lo, h)| self.node(lo, h)).collect()
+    }
+
+    /// The root of the range as it was with `size` leaves.
+    pub fn root(&self, size: usize) -> Option<blake3::Hash> {
+        bag(&self.peaks(size))
+    }
+
+    /// The inclusion proof of the leaf `nth` in the range of `size` leaves, for `nth < size`.
+    ///
+    /// It's the path from the leaf to the peak of its mountain, from the bottom, then the bagging
+    /// of the peaks at its right if any, then the peaks at its left, from the right.
+    pub fn path(&self, nth: usize, size: usize) -> Vec<blake3::Hash> {
+        let mountains: Vec<_> = mountains(size).collect();
+        let j = mountains
+            .iter()
+            .position(|&(lo, h)| nth < lo + (1 << h))
+            .unwrap();
+        let mut path: Vec<_> = (0..mountains[j].1)
+            .map(|level| self.node(((nth >> level) ^ 1) << level, level))
+            .collect();
+        let right: Vec<_> = mountains[j + 1..]
+            .iter()
+            .map(|&(lo, h)| self.node(lo, h))
+            .collect();
+        path.extend(bag(&right));
+        path.extend(mountains[..j].iter().rev().map(|&(lo, h)| self.node(lo, h)));
+        path
+    }
+
+    /// The ancestry proof of the range of `to` leaves from the one of `from` leaves, for
+    /// `from <= to <= len`.
+    ///
+    /// Every mountain at `from` is a subtree of a mountain at `to`. The proof is made of the
+    /// nodes which, with the peaks at `from`, give the peaks at `to`. See [verify_ancestry].
+    pub fn ancestry(&self, from: usize, to: usize) -> Vec<blake3::Hash> {
+        let mut hashes = Vec::new();
+        for (lo, h) in mountains(to) {
+            self.ancestry_into(from, lo, h, &mut hashes);
+        }
+        hashes
+    }
+
+    fn ancestry_into(&self, from: usize, lo: usize, height: usize, hashes: &mut Vec<blake3::Hash>) {
+        if lo >= from {
+            hashes.push(self.node(lo, height));
+        } else if lo + (1 << height) > from {
+            self.ancestry_into(from, lo, height - 1, hashes);
+            self.ancestry_into(from, lo + (1 << (height - 1)), height - 1, hashes);
+        }
+        // otherwise it's a peak at `from`.
+    }
+}
+
+/// The length of the inclusion proof of the leaf `nth` in the range of `size` leaves, for
+/// `nth < size`.
+pub fn path_len(nth: usize, size: usize) -> usize {
+    let mountains: Vec<_> = mountains(size).collect();
+    let j = mountains
+        .iter()
+        .position(|&(lo, h)| nth < lo + (1 << h))
+        .unwrap();
+    mountains[j].1 + usize::from(j + 1 < mountains.len()) + j
+}
+
+/// Recompute the root from a leaf hash and its inclusion proof (see [Mmr::path]).
+///
+/// The whole path is always hashed, so a path which doesn't fit `(nth, size)` gives a wrong
+/// root rather than an error.
+pub fn root_from_path(
+    leaf: blake3::Hash,
+    nth: usize,
+    size: usize,
+    path: &[blake3::Hash],
+) -> blake3::Hash {
+    let mountains: Vec<_> = mountains(size).collect();
+    let (j, height) = mountains
+        .iter()
+        .position(|&(lo, h)| nth < lo + (1 << h))
+        .map_or((mountains.len(), 0), |j| (j, mountains[j].1));
+    let bagged_right = j + 1 < mountains.len();
+    path.iter().enumerate().fold(leaf, |hash, (i, p)| {
+        if i < height {
+            if nth >> i & 1 == 1 {
+                node_hash(p, &hash)
+            } else {
+                node_hash(&hash, p)
+            }
+        } else if i == height && bagged_right {
+            node_hash(&hash, p)
+        } else {
+            node_hash(p, &hash)
+        }
+    })
+}
+
+/// Check the range of `to` leaves with the root `new_root` is an extension of the one of `from`
+/// leaves with the root `old_root` (`None` when empty), given the `peaks` at `from` and the
+/// ancestry proof `hashes` (see [Mmr::ancestry]).
+pub fn verify_ancestry(
+    from: usize,
+    to: usize,
+    old_root: Option<blake3::Hash>,
+    new_root: blake3::Hash,
+    peaks: &[blake3::Hash],
+    hashes: &[blake3::Hash],
+) -> bool {
+    if from > to || peaks.len() != from.count_ones() as usize || bag(peaks) != old_root {
+        return false;
+    }
+    let mut peaks = peaks.iter();
+    let mut hashes = hashes.iter();
+    let new_peaks: Option<Vec<_>> = mountains(to)
+        .map(|(lo, h)| climb(from, lo, h, &mut peaks, &mut hashes))
+        .collect();
+    new_peaks.and_then(|new_peaks| bag(&new_peaks)) == Some(new_root)
+        && peaks.next().is_none()
+        && hashes.next().is_none()
+}
+
+// Compute the node of height `height` starting at the leaf `lo`, from the peaks at `from` and
+// the nodes of the ancestry proof, in the order [Mmr::ancestry] gives them.
+fn climb<'a>(
+    from: usize,
+    lo: usize,
+    height: usize,
+    peaks: &mut impl Iterator<Item = &'a blake3::Hash>,
+    hashes: &mut impl Iterator<Item = &'a blake3::Hash>,
+) -> Option<blake3::Hash> {
+    if lo >= from {
+        hashes.next().copied()
+    } else if lo + (1 << height) <= from {
+        peaks.next().copied()
+    } else {
+        let left = climb(from, lo, height - 1, peaks, hashes)?;
+        let right = climb(from, lo + (1 << (height - 1)), height - 1, peaks, hashes)?;
+        Some(node_hash(&left, &right))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mmr(size: u8) -> Mmr {
+        let mut mmr = Mmr::default();
+        for i in 0..size {
+            mmr.push(blake3::hash(&[i]));
+        }
+        mmr
+    }
+
+    #[test]
+    fn peaks() {
+        let mmr = mmr(11);
+        let h = |i: u8| blake3::hash(&[i]);
+        assert_eq!(mmr.leaves().len(), 11);
+        assert_eq!(mmr.root(0), None);
+        assert_eq!(mmr.peaks(1), vec![h(0)]);
+        assert_eq!(mmr.peaks(3), vec![node_hash(&h(0), &h(1)), h(2)]);
+        // 11 = 8 + 2 + 1
+        let peaks = mmr.peaks(11);
+        assert_eq!(peaks.len(), 3);
+        assert_eq!(peaks[1], node_hash(&h(8), &h(9)));
+        assert_eq!(peaks[2], h(10));
+        assert_eq!(
+            mmr.root(11),
+            Some(node_hash(&peaks[0], &node_hash(&peaks[1], &peaks[2])))
+        );
+    }
+
+    #[test]
+    // Nodes never change: the past versions are the ranges built with less leaves.
+    fn history() {
+        let full = mmr(33);
+        for size in 0..=33 {
+            assert_eq!(full.peaks(size as usize), mmr(size).peaks(size as usize));
+        }
+    }
+
+    #[test]
+    fn inclusion() {
+        let mmr = mmr(33);
+        for size in 1..=33 {
+            let root = mmr.root(size).unwrap();
+            for nth in 0..size {
+                let leaf = mmr.leaves()[nth];
+                let path = mmr.path(nth, size);
+                assert_eq!(root_from_path(leaf, nth, size, &path), root);
+                // the index is bound by the proof.
+                if size > 1 {
+                    assert_ne!(root_from_path(leaf, (nth + 1) % size, size, &path), root);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ancestry() {
+        let mmr = mmr(33);
+        for to in 1..=33 {
+            let new_root = mmr.root(to).unwrap();
+            for from in 0..=to {
+                let old_root = mmr.root(from);
+                let peaks = mmr.peaks(from);
+                let hashes = mmr.ancestry(from, to);
+                assert!(verify_ancestry(
+                    from, to, old_root, new_root, &peaks, &hashes
+                ));
+                if from > 0 {
+                    let other = mmr.root(from - 1);
+                    assert!(!verify_ancestry(from, to, other, new_root, &peaks, &hashes));
+                }
+                if to < 33 {
+                    let other = mmr.root(to + 1).unwrap();
+                    assert!(!verify_ancestry(from, to, old_root, other, &peaks, &hashes));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn from_leaves() {
+        for size in 0..40u8 {
+            let leaves = (0..size).map(|i| blake3::hash(&[i])).collect();
+            assert_eq!(Mmr::from_leaves(leaves), mmr(size));
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! Work split between threads with the `parallel` feature, run sequentially without it.
+use alloc::vec::Vec;
+
+/// Under this number of hashes to compute, the work is not worth a task of its own.
+pub const MIN_LEN: usize = 1 << 12;
+
+/// Run `a` and `b`, in parallel when they cover `len` elements or more.
+#[cfg_attr(not(feature = "parallel"), allow(unused_variables))]
+pub fn join<A, B, RA, RB>(len: usize, a: A, b: B) -> (RA, RB)
+where
+    A: FnOnce() -> RA + Send,
+    B: FnOnce() -> RB + Send,
+    RA: Send,
+    RB: Send,
+{
+    #[cfg(feature = "parallel")]
+    if len >= MIN_LEN {
+        return rayon::join(a, b);
+    }
+    (a(), b())
+}
+
+/// Map `f` over `items`, in parallel when there are `min_len` of them or more.
+#[cfg_attr(not(feature = "parallel"), allow(unused_variables))]
+pub fn map<T, R, F>(items: &[T], min_len: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    #[cfg(feature = "parallel")]
+    if items.len() >= min_len {
+        use rayon::prelude::*;
+        return items.par_iter().map(&f).collect();
+    }
+    items.iter().map(f).collect()
+}
+
+/// Map `f` over the consecutive pairs of `hashes`, an odd last one being dropped.
+pub fn pairs<F>(hashes: &[blake3::Hash], f: F) -> Vec<blake3::Hash>
+where
+    F: Fn(&blake3::Hash, &blake3::Hash) -> blake3::Hash + Sync,
+{
+    #[cfg(feature = "parallel")]
+    if hashes.len() >= MIN_LEN {
+        use rayon::prelude::*;
+        return hashes
+            .par_chunks_exact(2)
+            .map(|pair| f(&pair[0], &pair[1]))
+            .collect();
+    }
+    hashes
+        .chunks_exact(2)
+        .map(|pair| f(&pair[0], &pair[1]))
+        .collect()
+}
+
+/// Map `f` over `0..len`, in parallel when `len` is [MIN_LEN] or more.
+pub fn indices<R, F>(len: usize, f: F) -> Vec<R>
+where
+    R: Send,
+    F: Fn(usize) -> R + Sync,
+{
+    #[cfg(feature = "parallel")]
+    if len >= MIN_LEN {
+        use rayon::prelude::*;
+        return (0..len).into_par_iter().map(&f).collect();
+    }
+    (0..len).map(f).collect()
+}
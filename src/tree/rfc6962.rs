@@ -0,0 +1,440 @@
+//! The Merkle tree algorithms of RFC 6962 (Certificate Transparency), section 2.1.
+//!
+//! They work on the list of leaf hashes `D[n]` of the RFC. Hashes are SHA-256 digests, as in
+//! the RFC, stored in [blake3::Hash] so they can travel in the same [Proof](super::Proof) type
+//! as the other layout.
+use super::levels::Levels;
+use alloc::vec::Vec;
+use sha2::{Digest, Sha256};
+
+/// Hash of a leaf: `SHA-256(0x00 || entry)`.
+pub fn leaf_hash(entry: &[u8]) -> blake3::Hash {
+    let digest: [u8; 32] = Sha256::new()
+        .chain_update([0x00])
+        .chain_update(entry)
+        .finalize()
+        .into();
+    blake3::Hash::from(digest)
+}
+
+/// Hash of a node: `SHA-256(0x01 || left || right)`.
+pub fn node_hash(left: &blake3::Hash, right: &blake3::Hash) -> blake3::Hash {
+    let digest: [u8; 32] = Sha256::new()
+        .chain_update([0x01])
+        .chain_update(left.as_bytes())
+        .chain_update(right.as_bytes())
+        .finalize()
+        .into();
+    blake3::Hash::from(digest)
+}
+
+/// The largest power of two smaller than `n`, for `n > 1`. It's 0 for the trees of a single
+/// leaf or none, which have no split.
+pub fn split(n: usize) -> usize {
+    if n < 2 {
+        return 0;
+    }
+    1 << (usize::BITS - 1 - (n - 1).leading_zeros())
+}
+
+/// `MTH(D[n])`. The root of the empty list is `SHA-256()`.
+pub fn root(leaves: &[blake3::Hash]) -> blake3::Hash {
+    match leaves.len() {
+        0 => blake3::Hash::from(<[u8; 32]>::from(Sha256::digest([]))),
+        1 => leaves[0],
+        n => {
+            let (left, right) = leaves.split_at(split(n));
+            let (left, right) = super::parallel::join(n, || root(left), || root(right));
+            node_hash(&left, &right)
+        }
+    }
+}
+
+/// The tree of a list of leaf hashes, kept as its perfect subtrees: `MTH` of the leaves
+/// `i * 2^h .. (i + 1) * 2^h` is the node of height `h` of the [Levels].
+///
+/// The subtrees splitting a tree of the RFC into its left and right parts are all of these, so
+/// any past root and path is computed in O(log n) hashes.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Subtrees(Levels);
+
+impl Subtrees {
+    /// The tree of the given leaf hashes, built level by level.
+    pub fn from_leaves(leaves: Vec<blake3::Hash>) -> Self {
+        Subtrees(Levels::from_leaves(leaves, node_hash))
+    }
+
+    pub fn push(&mut self, leaf: blake3::Hash) {
+        self.0.push(leaf, node_hash);
+    }
+
+    /// `MTH(D[lo:hi])`, for `lo < hi`, the range being one of the RFC: a perfect one is aligned
+    /// on its size.
+    fn range_root(&self, lo: usize, hi: usize) -> blake3::Hash {
+        let n = hi - lo;
+        if n.is_power_of_two() {
+            return self.0.node(lo, n.trailing_zeros() as usize);
+        }
+        let k = split(n);
+        node_hash(&self.range_root(lo, lo + k), &self.range_root(lo + k, hi))
+    }
+
+    /// `MTH(D[size])`, the root of the tree as it was with `size` leaves. `None` when empty.
+    pub fn root(&self, size: usize) -> Option<blake3::Hash> {
+        (size > 0).then(|| self.range_root(0, size))
+    }
+
+    /// `PATH(m, D[size])`: the audit path of the leaf `m` in the tree of `size` leaves, from the
+    /// bottom, for `m < size`.
+    pub fn path(&self, m: usize, size: usize) -> Vec<blake3::Hash> {
+        let mut path = Vec::new();
+        self.path_into(m, 0, size, &mut path);
+        path
+    }
+
+    // The path of the leaf `lo + m` in `D[lo:hi]`.
+    fn path_into(&self, m: usize, lo: usize, hi: usize, path: &mut Vec<blake3::Hash>) {
+        if hi - lo == 1 {
+            return;
+        }
+        let k = split(hi - lo);
+        if m < k {
+            self.path_into(m, lo, lo + k, path);
+            path.push(self.range_root(lo + k, hi));
+        } else {
+            self.path_into(m - k, lo + k, hi, path);
+            path.push(self.range_root(lo, lo + k));
+        }
+    }
+
+    /// `PROOF(m, D[size])`: the consistency proof from the first `m` leaves, for
+    /// `0 < m <= size`.
+    pub fn consistency(&self, m: usize, size: usize) -> Vec<blake3::Hash> {
+        let mut proof = Vec::new();
+        self.subproof(m, 0, size, true, &mut proof);
+        proof
+    }
+
+    // `SUBPROOF(m, D[lo:hi], complete)`.
+    fn subproof(
+        &self,
+        m: usize,
+        lo: usize,
+        hi: usize,
+        complete: bool,
+        proof: &mut Vec<blake3::Hash>,
+    ) {
+        if m == hi - lo {
+            if !complete {
+                proof.push(self.range_root(lo, hi));
+            }
+            return;
+        }
+        let k = split(hi - lo);
+        if m <= k {
+            self.subproof(m, lo, lo + k, complete, proof);
+            proof.push(self.range_root(lo + k, hi));
+        } else {
+            self.subproof(m - k, lo + k, hi, false, proof);
+            proof.push(self.range_root(lo, lo + k));
+        }
+    }
+}
+
+/// The length of `PATH(m, D[n])`.
+pub fn path_len(mut m: usize, mut n: usize) -> usize {
+    let mut len = 0;
+    while n > 1 {
+        let k = split(n);
+        if m < k {
+            n = k;
+        } else {
+            m -= k;
+            n -= k;
+        }
+        len += 1;
+    }
+    len
+}
+
+/// Recompute the root from a leaf hash and its audit path (RFC 9162, 2.1.3.2).
+///
+/// The whole path is always hashed, so a path which doesn't fit `(index, size)` gives a wrong
+/// root rather than an error.
+pub fn root_from_path(
+    leaf: blake3::Hash,
+    index: usize,
+    size: usize,
+    path: &[blake3::Hash],
+) -> blake3::Hash {
+    let mut fnode = index;
+    let mut snode = size.saturating_sub(1);
+    let mut hash = leaf;
+    for p in path {
+        if fnode & 1 == 1 || fnode == snode {
+            hash = node_hash(p, &hash);
+            if fnode & 1 == 0 {
+                while fnode & 1 == 0 && fnode != 0 {
+                    fnode >>= 1;
+                    snode >>= 1;
+                }
+            }
+        } else {
+            hash = node_hash(&hash, p);
+        }
+        fnode >>= 1;
+        snode >>= 1;
+    }
+    hash
+}
+
+/// Check a consistency proof between the trees of size `first` and `second`
+/// (RFC 9162, 2.1.4.2), for `0 < first <= second`.
+pub fn verify_consistency(
+    first: usize,
+    second: usize,
+    first_hash: blake3::Hash,
+    second_hash: blake3::Hash,
+    proof: &[blake3::Hash],
+) -> bool {
+    if first == 0 || first > second {
+        return false;
+    }
+    if first == second {
+        return proof.is_empty() && first_hash == second_hash;
+    }
+    let mut path = proof.to_vec();
+    if first.is_power_of_two() {
+        path.insert(0, first_hash);
+    }
+    let Some((&start, path)) = path.split_first() else {
+        return false;
+    };
+    let mut fnode = first - 1;
+    let mut snode = second - 1;
+    while fnode & 1 == 1 {
+        fnode >>= 1;
+        snode >>= 1;
+    }
+    let mut fhash = start;
+    let mut shash = start;
+    for c in path {
+        if snode == 0 {
+            return false;
+        }
+        if fnode & 1 == 1 || fnode == snode {
+            fhash = node_hash(c, &fhash);
+            shash = node_hash(c, &shash);
+            if fnode & 1 == 0 {
+                while fnode & 1 == 0 && fnode != 0 {
+                    fnode >>= 1;
+                    snode >>= 1;
+                }
+            }
+        } else {
+            shash = node_hash(&shash, c);
+        }
+        fnode >>= 1;
+        snode >>= 1;
+    }
+    fhash == first_hash && shash == second_hash && snode == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The test vectors of the Certificate Transparency reference implementations.
+    const LEAVES: [&str; 8] = [
+        "",
+        "00",
+        "10",
+        "2021",
+        "3031",
+        "40414243",
+        "5051525354555657",
+        "606162636465666768696a6b6c6d6e6f",
+    ];
+    const ROOTS: [&str; 8] = [
+        "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+        "fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125",
+        "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77",
+        "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+        "4e3bbb1f7b478dcfe71fb631631519a3bca12c9aefca1612bfce4c13a86264d4",
+        "76e67dadbcdf1e10e1b74ddc608abd2f98dfb16fbce75277b5232a127f2087ef",
+        "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c",
+        "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328",
+    ];
+    // (index, size, path)
+    const INCLUSIONS: [(usize, usize, &[&str]); 5] = [
+        (0, 1, &[]),
+        (
+            0,
+            8,
+            &[
+                "96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7",
+                "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                "6b47aaf29ee3c2af9af889bc1fb9254dabd31177f16232dd6aab035ca39bf6e4",
+            ],
+        ),
+        (
+            5,
+            8,
+            &[
+                "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+                "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+                "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+            ],
+        ),
+        (
+            2,
+            3,
+            &["fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125"],
+        ),
+        (
+            1,
+            5,
+            &[
+                "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+                "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+            ],
+        ),
+    ];
+    // (first, second, proof)
+    const CONSISTENCIES: [(usize, usize, &[&str]); 4] = [
+        (1, 1, &[]),
+        (
+            1,
+            8,
+            &[
+                "96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7",
+                "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                "6b47aaf29ee3c2af9af889bc1fb9254dabd31177f16232dd6aab035ca39bf6e4",
+            ],
+        ),
+        (
+            6,
+            8,
+            &[
+                "0ebc5d3437fbe2db158b9f126a1d118e308181031d0a949f8dededebc558ef6a",
+                "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+                "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+            ],
+        ),
+        (
+            2,
+            5,
+            &[
+                "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+            ],
+        ),
+    ];
+
+    fn leaves() -> Vec<blake3::Hash> {
+        LEAVES.iter().map(|l| leaf_hash(&hex_bytes(l))).collect()
+    }
+
+    fn hex_bytes(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn hashes(hexes: &[&str]) -> Vec<blake3::Hash> {
+        hexes
+            .iter()
+            .map(|h| blake3::Hash::from_hex(h).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn roots() {
+        let leaves = leaves();
+        let tree = Subtrees::from_leaves(leaves.clone());
+        for (n, expected) in ROOTS.iter().enumerate() {
+            assert_eq!(root(&leaves[..n + 1]).to_hex().as_str(), *expected);
+            assert_eq!(tree.root(n + 1), Some(root(&leaves[..n + 1])));
+        }
+        // the hash of the empty string.
+        assert_eq!(
+            root(&[]).to_hex().as_str(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(tree.root(0), None);
+        assert_eq!(split(0), 0);
+        assert_eq!(split(1), 0);
+    }
+
+    #[test]
+    // A tree pushed leaf by leaf is the one built from all of them.
+    fn push() {
+        let mut tree = Subtrees::default();
+        for leaf in leaves() {
+            tree.push(leaf);
+        }
+        assert_eq!(tree, Subtrees::from_leaves(leaves()));
+    }
+
+    #[test]
+    fn inclusion() {
+        let leaves = leaves();
+        let tree = Subtrees::from_leaves(leaves.clone());
+        for (index, size, expected) in INCLUSIONS {
+            let expected = hashes(expected);
+            assert_eq!(tree.path(index, size), expected);
+            let root = root(&leaves[..size]);
+            assert_eq!(root_from_path(leaves[index], index, size, &expected), root);
+            // the index is bound by the proof.
+            if size > 1 {
+                assert_ne!(
+                    root_from_path(leaves[index], (index + 1) % size, size, &expected),
+                    root
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn consistency_vectors() {
+        let leaves = leaves();
+        let tree = Subtrees::from_leaves(leaves.clone());
+        for (first, second, expected) in CONSISTENCIES {
+            let expected = hashes(expected);
+            assert_eq!(tree.consistency(first, second), expected);
+            let first_hash = root(&leaves[..first]);
+            let second_hash = root(&leaves[..second]);
+            assert!(verify_consistency(
+                first,
+                second,
+                first_hash,
+                second_hash,
+                &expected
+            ));
+            assert!(
+                !verify_consistency(first, second, second_hash, first_hash, &expected)
+                    || first == second
+            );
+        }
+    }
+
+    #[test]
+    fn all_consistencies() {
+        let leaves = leaves();
+        let tree = Subtrees::from_leaves(leaves.clone());
+        for second in 1..=8 {
+            for first in 1..=second {
+                let proof = tree.consistency(first, second);
+                assert!(verify_consistency(
+                    first,
+                    second,
+                    root(&leaves[..first]),
+                    root(&leaves[..second]),
+                    &proof
+                ));
+            }
+        }
+    }
+}
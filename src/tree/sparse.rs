@@ -0,0 +1,311 @@
+//! Sparse Merkle tree: a verifiable map from 256 bits keys to elements.
+//!
+//! The keys are the paths of a binary tree of height 256, from their most significant bit
+//! (`0` is left). The tree is compact: an empty subtree hashes to zero and a subtree holding a
+//! single element is replaced by its leaf, so its size only depends on the number of elements.
+//! Leaves are `BLAKE3(0x00 || key || hash)` and nodes `BLAKE3(left || right)`.
+use super::hash::node_hash;
+use super::{hash_deser, hash_ser, hash_vec_deser, hash_vec_ser, Deserialize, Serialize};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Hash of an empty subtree.
+const EMPTY: blake3::Hash = blake3::Hash::from_bytes([0; 32]);
+
+/// The sparse merkel tree storage, a map whose elements can be proven present or absent.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SMap<D> {
+    /// Sorted by key, so the elements of a subtree are contiguous.
+    entries: Vec<Entry<D>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry<D> {
+    #[serde(deserialize_with = "hash_deser")]
+    #[serde(serialize_with = "hash_ser")]
+    key: blake3::Hash,
+    #[serde(deserialize_with = "hash_deser")]
+    #[serde(serialize_with = "hash_ser")]
+    hash: blake3::Hash,
+    data: D,
+}
+
+/// A proof of the presence or the absence of a key in a [SMap].
+///
+/// It can be obtained by a call to [SMap::proof], and is returned at every insertion by
+/// [SMap::insert].
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyProof {
+    #[serde(deserialize_with = "hash_deser")]
+    #[serde(serialize_with = "hash_ser")]
+    key: blake3::Hash,
+    /// For an absent key, the element found in place of the key, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    other: Option<Leaf>,
+    /// The hashes of the brothers of the path of the key, from the top.
+    #[serde(deserialize_with = "hash_vec_deser")]
+    #[serde(serialize_with = "hash_vec_ser")]
+    hashes: Vec<blake3::Hash>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Leaf {
+    #[serde(deserialize_with = "hash_deser")]
+    #[serde(serialize_with = "hash_ser")]
+    key: blake3::Hash,
+    #[serde(deserialize_with = "hash_deser")]
+    #[serde(serialize_with = "hash_ser")]
+    hash: blake3::Hash,
+}
+
+fn leaf_hash(key: &blake3::Hash, hash: &blake3::Hash) -> blake3::Hash {
+    blake3::Hasher::new()
+        .update(&[0x00])
+        .update(key.as_bytes())
+        .update(hash.as_bytes())
+        .finalize()
+}
+
+/// The bit of `key` choosing the side at `depth`, `true` for the right.
+fn bit(key: &blake3::Hash, depth: usize) -> bool {
+    key.as_bytes()[depth / 8] >> (7 - depth % 8) & 1 == 1
+}
+
+impl KeyProof {
+    /// The key this `KeyProof` is about.
+    pub fn key(&self) -> blake3::Hash {
+        self.key
+    }
+
+    /// Compute the root of the map the proof was made for, the key being bound to the element
+    /// of hash `hash`, or absent when `None`.
+    ///
+    /// Returns `None` when the proof can't prove that, e.g. the absence of a key whose proof
+    /// holds another element which is not on the path of the key.
+    pub fn root(&self, hash: Option<blake3::Hash>) -> Option<blake3::Hash> {
+        let depth = self.hashes.len();
+        if depth > 256 {
+            return None;
+        }
+        let start = match (hash, &self.other) {
+            (Some(hash), None) => leaf_hash(&self.key, &hash),
+            (Some(_), Some(_)) => return None,
+            (None, None) => EMPTY,
+            (None, Some(other)) => {
+                if other.key == self.key
+                    || (0..depth).any(|d| bit(&other.key, d) != bit(&self.key, d))
+                {
+                    return None;
+                }
+                leaf_hash(&other.key, &other.hash)
+            }
+        };
+        Some(
+            self.hashes
+                .iter()
+                .enumerate()
+                .rfold(start, |ag, (depth, h)| {
+                    if bit(&self.key, depth) {
+                        node_hash(h, &ag)
+                    } else {
+                        node_hash(&ag, h)
+                    }
+                }),
+        )
+    }
+
+    /// Check the key is bound to the element of hash `hash`, or absent when `None`, in the map
+    /// of root `root`.
+    pub fn verify(&self, hash: Option<blake3::Hash>, root: blake3::Hash) -> bool {
+        self.root(hash) == Some(root)
+    }
+}
+
+impl<D> SMap<D> {
+    /// Create an empty map.
+    pub fn new() -> Self {
+        Self { entries: vec![] }
+    }
+
+    /// The number of elements in the map.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn find(&self, key: &blake3::Hash) -> Result<usize, usize> {
+        self.entries
+            .binary_search_by(|e| e.key.as_bytes().cmp(key.as_bytes()))
+    }
+
+    /// Bind `key` to an element, replacing the previous one, and returns the proof of the key.
+    pub fn insert(&mut self, key: blake3::Hash, hash: blake3::Hash, data: D) -> KeyProof {
+        match self.find(&key) {
+            Ok(i) => self.entries[i] = Entry { key, hash, data },
+            Err(i) => self.entries.insert(i, Entry { key, hash, data }),
+        }
+        self.proof(key)
+    }
+
+    /// Compute the root of the tree. It's zero for an empty map.
+    ///
+    /// In a client server environment, it can only be computed by the server and so is not
+    /// confiable by the client.
+    pub fn root(&self) -> blake3::Hash {
+        subtree_hash(&self.entries, 0)
+    }
+
+    /// Returns the proof of the presence or the absence of `key`.
+    pub fn proof(&self, key: blake3::Hash) -> KeyProof {
+        let mut entries = &self.entries[..];
+        let mut depth = 0;
+        let mut hashes = Vec::new();
+        while entries.len() > 1 {
+            let mid = entries.partition_point(|e| !bit(&e.key, depth));
+            let (path, brother) = if bit(&key, depth) {
+                (&entries[mid..], &entries[..mid])
+            } else {
+                (&entries[..mid], &entries[mid..])
+            };
+            hashes.push(subtree_hash(brother, depth + 1));
+            entries = path;
+            depth += 1;
+        }
+        let other = match entries {
+            [e] if e.key != key => Some(Leaf {
+                key: e.key,
+                hash: e.hash,
+            }),
+            _ => None,
+        };
+        KeyProof { key, other, hashes }
+    }
+
+    /// The hashes of the elements, in the order of their keys.
+    pub fn hashes(&self) -> impl Iterator<Item = blake3::Hash> + '_ {
+        self.entries.iter().map(|e| e.hash)
+    }
+
+    /// The hash of the element bound to `key`.
+    pub fn get_hash(&self, key: &blake3::Hash) -> Option<blake3::Hash> {
+        self.find(key).ok().map(|i| self.entries[i].hash)
+    }
+}
+
+impl<D: Clone> SMap<D> {
+    /// Get the element bound to `key`.
+    pub fn get(&self, key: &blake3::Hash) -> Option<D> {
+        self.find(key).ok().map(|i| self.entries[i].data.clone())
+    }
+}
+
+/// Hash of the subtree at `depth` holding the sorted `entries`.
+fn subtree_hash<D>(entries: &[Entry<D>], depth: usize) -> blake3::Hash {
+    match entries {
+        [] => EMPTY,
+        [e] => leaf_hash(&e.key, &e.hash),
+        _ => {
+            let mid = entries.partition_point(|e| !bit(&e.key, depth));
+            node_hash(
+                &subtree_hash(&entries[..mid], depth + 1),
+                &subtree_hash(&entries[mid..], depth + 1),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(i: u8) -> blake3::Hash {
+        blake3::hash(&[i, 0xff])
+    }
+
+    #[test]
+    fn empty() {
+        let map = SMap::<u8>::new();
+        assert_eq!(map.root(), EMPTY);
+        let proof = map.proof(key(0));
+        assert!(proof.hashes.is_empty());
+        assert!(proof.verify(None, EMPTY));
+        assert!(!proof.verify(Some(blake3::hash(&[0])), EMPTY));
+    }
+
+    #[test]
+    fn single() {
+        let mut map = SMap::new();
+        let proof = map.insert(key(0), blake3::hash(&[0]), 0);
+        // the only element is the root.
+        assert_eq!(map.root(), leaf_hash(&key(0), &blake3::hash(&[0])));
+        assert!(proof.verify(Some(blake3::hash(&[0])), map.root()));
+        let proof = map.proof(key(1));
+        assert!(proof.other.is_some());
+        assert!(proof.verify(None, map.root()));
+    }
+
+    #[test]
+    fn proofs() {
+        let mut map = SMap::new();
+        for i in 0u8..40 {
+            let proof = map.insert(key(i), blake3::hash(&[i]), i);
+            assert!(proof.verify(Some(blake3::hash(&[i])), map.root()));
+        }
+        let root = map.root();
+        for i in 0u8..40 {
+            let proof = map.proof(key(i));
+            assert_eq!(map.get(&key(i)), Some(i));
+            assert!(proof.verify(Some(blake3::hash(&[i])), root));
+            assert!(!proof.verify(Some(blake3::hash(&[i + 1])), root));
+            assert!(!proof.verify(None, root));
+        }
+        // absent keys, found in place of an empty subtree or of another element.
+        let mut others = 0;
+        for i in 40u8..120 {
+            let proof = map.proof(key(i));
+            others += proof.other.is_some() as usize;
+            assert_eq!(map.get(&key(i)), None);
+            assert!(proof.verify(None, root));
+            assert!(!proof.verify(Some(blake3::hash(&[i])), root));
+        }
+        assert!(others > 0 && others < 80);
+    }
+
+    #[test]
+    fn replace() {
+        let mut map = SMap::new();
+        for i in 0u8..10 {
+            map.insert(key(i), blake3::hash(&[i]), i);
+        }
+        let root = map.root();
+        let proof = map.insert(key(3), blake3::hash(&[33]), 33);
+        assert_eq!(map.len(), 10);
+        assert_eq!(map.get(&key(3)), Some(33));
+        assert_ne!(map.root(), root);
+        assert!(proof.verify(Some(blake3::hash(&[33])), map.root()));
+        assert!(!proof.verify(Some(blake3::hash(&[3])), map.root()));
+    }
+
+    #[test]
+    // The absence of a key can't be proven with an element which is not on its path.
+    fn other_off_path() {
+        let mut map = SMap::new();
+        for i in 0u8..10 {
+            map.insert(key(i), blake3::hash(&[i]), i);
+        }
+        let root = map.root();
+        let mut proof = map.proof(key(3));
+        // differs from the key 3 from the first bit.
+        let mut bytes = *key(3).as_bytes();
+        bytes[0] ^= 0x80;
+        proof.key = blake3::Hash::from_bytes(bytes);
+        proof.other = Some(Leaf {
+            key: key(3),
+            hash: blake3::hash(&[3]),
+        });
+        assert!(!proof.verify(None, root));
+    }
+}
@@ -0,0 +1,391 @@
+//! Verification of the proofs with the reason of a failure.
+use super::{Layout, Proof};
+use core::fmt;
+
+/// An element proven to be the `nth` of a store of `size` elements with the root `root`.
+///
+/// Returned by [Proof::verify] and [Proof::verify_append].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifiedInclusion {
+    nth: usize,
+    size: usize,
+    hash: blake3::Hash,
+    root: blake3::Hash,
+}
+
+/// Why a [Proof] failed to verify.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The proof was made for a store of another layout.
+    WrongLayout { expected: Layout, found: Layout },
+    /// The proof is about another element.
+    WrongIndex { expected: usize, found: usize },
+    /// The element is beyond the size of the store.
+    IndexOutOfRange { nth: usize, size: usize },
+    /// The proof was made for a store of another size.
+    WrongSize { expected: usize, found: usize },
+    /// The proof doesn't hold the size of the store, which its layout needs.
+    MissingSize,
+    /// The proof doesn't have the number of hashes of a proof of this element at this size.
+    WrongLength { expected: usize, found: usize },
+    /// The root recomputed from the element and the proof is not the expected one: the element
+    /// or the proof is wrong.
+    RootMismatch { computed: blake3::Hash },
+    /// The root before the push recomputed from the proof is not the expected one: the server
+    /// changed the store before pushing.
+    PreviousRootMismatch { computed: Option<blake3::Hash> },
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::WrongLayout { expected, found } => write!(
+                f,
+                "the proof is for the {:?} layout instead of {:?}",
+                found, expected
+            ),
+            VerifyError::WrongIndex { expected, found } => write!(
+                f,
+                "the proof is about the element {} instead of {}",
+                found, expected
+            ),
+            VerifyError::IndexOutOfRange { nth, size } => {
+                write!(f, "the element {} is beyond the {} elements", nth, size)
+            }
+            VerifyError::WrongSize { expected, found } => write!(
+                f,
+                "the proof is for {} elements instead of {}",
+                found, expected
+            ),
+            VerifyError::MissingSize => write!(f, "the proof doesn't hold the store size"),
+            VerifyError::WrongLength { expected, found } => {
+                write!(f, "the proof has {} hashes instead of {}", found, expected)
+            }
+            VerifyError::RootMismatch { computed } => write!(
+                f,
+                "the element and the proof give the root {}, the data or the proof is wrong",
+                computed
+            ),
+            VerifyError::PreviousRootMismatch {
+                computed: Some(computed),
+            } => write!(
+                f,
+                "the store had the root {} before the push, not the trusted one",
+                computed
+            ),
+            VerifyError::PreviousRootMismatch { computed: None } => {
+                write!(
+                    f,
+                    "the store was empty before the push, not the trusted one"
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for VerifyError {}
+
+impl VerifiedInclusion {
+    /// The index of the element.
+    pub fn nth(&self) -> usize {
+        self.nth
+    }
+
+    /// The size of the store the element was proven in.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The hash of the element.
+    pub fn hash(&self) -> blake3::Hash {
+        self.hash
+    }
+
+    /// The root of the store the element was proven in.
+    pub fn root(&self) -> blake3::Hash {
+        self.root
+    }
+}
+
+impl Proof {
+    /// Check this proof shows the element of hash `hash` is the `nth` of the store of `size`
+    /// elements with the root `root`, whose elements are placed with `layout`.
+    ///
+    /// Unlike [Proof::prove_on], it checks the proof is about `nth` and has the shape of a
+    /// proof of `nth` at `size` in the expected layout, and tells why it failed. The layout
+    /// the proof claims is never trusted: it chooses how the root is recomputed.
+    pub fn verify(
+        &self,
+        hash: blake3::Hash,
+        nth: usize,
+        size: usize,
+        root: blake3::Hash,
+        layout: Layout,
+    ) -> Result<VerifiedInclusion, VerifyError> {
+        self.check_shape(layout, nth, size)?;
+        let computed = self.root_at(hash, size);
+        if computed != root {
+            return Err(VerifyError::RootMismatch { computed });
+        }
+        Ok(VerifiedInclusion {
+            nth,
+            size,
+            hash,
+            root,
+        })
+    }
+
+    /// Check this proof, returned by [HMap::push](super::HMap::push), shows the element of
+    /// hash `hash` was pushed to the store of `size` elements with the root `old_root` (`None`
+    /// when empty), whose elements are placed with `layout`.
+    ///
+    /// The returned inclusion holds the new root of the store.
+    pub fn verify_append(
+        &self,
+        hash: blake3::Hash,
+        size: usize,
+        old_root: Option<blake3::Hash>,
+        layout: Layout,
+    ) -> Result<VerifiedInclusion, VerifyError> {
+        // a full store can't be pushed to.
+        let new_size = size
+            .checked_add(1)
+            .ok_or(VerifyError::IndexOutOfRange { nth: size, size })?;
+        self.check_shape(layout, size, new_size)?;
+        let computed = self.hash();
+        if computed != old_root {
+            return Err(VerifyError::PreviousRootMismatch { computed });
+        }
+        Ok(VerifiedInclusion {
+            nth: size,
+            size: new_size,
+            hash,
+            root: self.root_at(hash, new_size),
+        })
+    }
+
+    fn check_shape(&self, layout: Layout, nth: usize, size: usize) -> Result<(), VerifyError> {
+        if self.layout != layout {
+            return Err(VerifyError::WrongLayout {
+                expected: layout,
+                found: self.layout,
+            });
+        }
+        if self.nth != nth {
+            return Err(VerifyError::WrongIndex {
+                expected: nth,
+                found: self.nth,
+            });
+        }
+        if nth >= size {
+            return Err(VerifyError::IndexOutOfRange { nth, size });
+        }
+        match self.size {
+            Some(found) if found != size => {
+                return Err(VerifyError::WrongSize {
+                    expected: size,
+                    found,
+                })
+            }
+            // these layouts need the size to rebuild the path.
+            None if self.layout != Layout::BitReversed => return Err(VerifyError::MissingSize),
+            _ => (),
+        }
+        let expected = self.layout.proof_len(nth, size);
+        if self.hashes.len() != expected {
+            return Err(VerifyError::WrongLength {
+                expected,
+                found: self.hashes.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::HMap;
+    use super::*;
+
+    const LAYOUTS: [Layout; 3] = [Layout::BitReversed, Layout::Rfc6962, Layout::Mmr];
+
+    #[test]
+    // The expected lengths are the ones of the proofs of the stores.
+    fn proof_len() {
+        for layout in LAYOUTS {
+            let mut store = HMap::with_layout(layout);
+            for size in 1..=40 {
+                store.push(blake3::hash(&[size as u8]), size);
+                for nth in 0..size {
+                    let proof = store.proof(nth).unwrap();
+                    assert_eq!(layout.proof_len(nth, size), proof.hashes.len());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn verify() {
+        for layout in LAYOUTS {
+            let mut store = HMap::with_layout(layout);
+            let mut root = None;
+            for i in 0..13u8 {
+                let hash = blake3::hash(&[i]);
+                let proof = store.push(hash, i);
+                let verified = proof.verify_append(hash, i as usize, root, layout).unwrap();
+                assert_eq!(verified.root(), store.root());
+                assert_eq!(
+                    proof.verify_append(hash, i as usize, Some(hash), layout),
+                    Err(VerifyError::PreviousRootMismatch { computed: root })
+                );
+                root = Some(store.root());
+            }
+            let root = store.root();
+            let hash = blake3::hash(&[5]);
+            let proof = store.proof(5).unwrap();
+            let verified = proof.verify(hash, 5, 13, root, layout).unwrap();
+            assert_eq!((verified.nth(), verified.size()), (5, 13));
+            assert_eq!(verified.hash(), hash);
+
+            assert_eq!(
+                proof.verify(hash, 6, 13, root, layout),
+                Err(VerifyError::WrongIndex {
+                    expected: 6,
+                    found: 5
+                })
+            );
+            assert_eq!(
+                proof.verify(hash, 5, 5, root, layout),
+                Err(VerifyError::IndexOutOfRange { nth: 5, size: 5 })
+            );
+            assert!(matches!(
+                proof.verify(blake3::hash(&[6]), 5, 13, root, layout),
+                Err(VerifyError::RootMismatch { .. })
+            ));
+            let mut short = store.proof(5).unwrap();
+            short.hashes.pop();
+            assert!(matches!(
+                short.verify(hash, 5, 13, root, layout),
+                Err(VerifyError::WrongLength { found, .. }) if found + 1 == proof.hashes.len()
+            ));
+        }
+    }
+
+    #[test]
+    fn wrong_size() {
+        for layout in LAYOUTS {
+            let mut store = HMap::with_layout(layout);
+            for i in 0..13u8 {
+                store.push(blake3::hash(&[i]), i);
+            }
+            let proof = store.proof(5).unwrap();
+            assert_eq!(proof.size(), Some(13));
+            assert_eq!(
+                proof.verify(blake3::hash(&[5]), 5, 12, store.root(), layout),
+                Err(VerifyError::WrongSize {
+                    expected: 12,
+                    found: 13
+                })
+            );
+        }
+    }
+
+    #[test]
+    // A proof is checked in the expected layout, not in the one it claims.
+    fn wrong_layout() {
+        for layout in LAYOUTS {
+            let mut store = HMap::with_layout(layout);
+            let mut old_root = None;
+            for i in 0..13u8 {
+                let proof = store.push(blake3::hash(&[i]), i);
+                for other in LAYOUTS.into_iter().filter(|other| *other != layout) {
+                    assert_eq!(
+                        proof.verify_append(blake3::hash(&[i]), i as usize, old_root, other),
+                        Err(VerifyError::WrongLayout {
+                            expected: other,
+                            found: layout
+                        })
+                    );
+                }
+                old_root = Some(store.root());
+            }
+            let hash = blake3::hash(&[5]);
+            for other in LAYOUTS.into_iter().filter(|other| *other != layout) {
+                let mut swapped = store.proof(5).unwrap();
+                swapped.layout = other;
+                assert_eq!(
+                    swapped.verify(hash, 5, 13, store.root(), layout),
+                    Err(VerifyError::WrongLayout {
+                        expected: layout,
+                        found: other
+                    })
+                );
+                let proof = store.proof(5).unwrap();
+                assert!(matches!(
+                    proof.verify(hash, 5, 13, store.root(), other),
+                    Err(VerifyError::WrongLayout { .. })
+                ));
+            }
+        }
+    }
+
+    #[test]
+    // Bit reversed proofs made before they held the size are checked against the given one.
+    fn legacy() {
+        let mut store = HMap::new();
+        for i in 0..13u8 {
+            store.push(blake3::hash(&[i]), i);
+        }
+        let mut proof = store.proof(5).unwrap();
+        proof.size = None;
+        let hash = blake3::hash(&[5]);
+        assert!(proof
+            .verify(hash, 5, 13, store.root(), Layout::BitReversed)
+            .is_ok());
+        // 5 has a sibling 13 at 14 elements, so the proof is one hash short.
+        assert!(matches!(
+            proof.verify(hash, 5, 14, store.root(), Layout::BitReversed),
+            Err(VerifyError::WrongLength { .. })
+        ));
+    }
+
+    #[test]
+    // Forged proofs with out of range numbers are rejected without overflowing.
+    fn forged() {
+        let hash = blake3::hash(b"forged");
+        let long = Proof {
+            nth: 3,
+            layout: Layout::BitReversed,
+            size: None,
+            hashes: vec![hash; 70],
+        };
+        assert_ne!(*long.prove_on(hash), hash);
+        assert!(long.hash().is_some());
+        for layout in LAYOUTS {
+            let last = Proof {
+                nth: usize::MAX,
+                layout,
+                size: Some(usize::MAX),
+                hashes: vec![hash],
+            };
+            let _ = (last.hash(), last.prove_on(hash));
+            assert!(matches!(
+                last.verify(hash, usize::MAX, usize::MAX, hash, layout),
+                Err(VerifyError::IndexOutOfRange { .. })
+            ));
+            assert_eq!(
+                last.verify_append(hash, usize::MAX, Some(hash), layout),
+                Err(VerifyError::IndexOutOfRange {
+                    nth: usize::MAX,
+                    size: usize::MAX
+                })
+            );
+            let huge = Proof { nth: 0, ..last };
+            assert!(matches!(
+                huge.verify(hash, 0, usize::MAX, hash, layout),
+                Err(VerifyError::WrongLength { .. })
+            ));
+        }
+    }
+}
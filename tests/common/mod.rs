@@ -0,0 +1,58 @@
+//! Helpers running `htree-server` for the integration tests.
+#![allow(dead_code)]
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::time::{Duration, Instant};
+use std::{env, fs, thread};
+
+// A running `htree-server`, killed on drop.
+pub struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+// An empty temporary directory, unique to the test `name` of this run.
+pub fn workdir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("htree-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+pub fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+// The command of a server listening on `port` of the loopback, run in `dir`.
+pub fn server_command(dir: &Path, port: u16) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_htree-server"));
+    cmd.current_dir(dir).arg("127.0.0.1").arg(port.to_string());
+    cmd
+}
+
+// Spawn the server of `cmd` and wait until it accepts connections on `port`.
+pub fn spawn_server(cmd: &mut Command, port: u16) -> Server {
+    let server = Server(cmd.spawn().unwrap());
+    let start = Instant::now();
+    while TcpStream::connect(("127.0.0.1", port)).is_err() {
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "server did not start"
+        );
+        thread::sleep(Duration::from_millis(50));
+    }
+    server
+}
+
+pub fn start_server(dir: &Path, port: u16) -> Server {
+    spawn_server(&mut server_command(dir, port), port)
+}
@@ -0,0 +1,108 @@
+//! Golden files of the binary encoding of the proofs.
+//!
+//! `tests/golden/<layout>.proofs` holds the root of a store of 13 elements, then the proof of
+//! each element, hex encoded, one per line. The proofs must keep being decoded and verified, and
+//! the current encoding must not change. Run with `HTREE_BLESS=1` to write the files of a new
+//! layout. `bit_reversed_legacy` holds proofs made before they held the size of the store.
+use htree_challenge::tree::*;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const SIZE: u8 = 13;
+
+fn golden(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.proofs", name))
+}
+
+fn store(layout: Layout) -> HMap<u8> {
+    let mut store = HMap::with_layout(layout);
+    for i in 0..SIZE {
+        store.push(blake3::hash(&[i]), i);
+    }
+    store
+}
+
+#[test]
+pub fn golden_files() {
+    for (name, layout) in [
+        ("bit_reversed", Layout::BitReversed),
+        ("rfc6962", Layout::Rfc6962),
+        ("mmr", Layout::Mmr),
+    ] {
+        let store = store(layout);
+        let path = golden(name);
+        if std::env::var_os("HTREE_BLESS").is_some() && !path.exists() {
+            let mut lines = vec![store.root().to_hex().to_string()];
+            lines.extend((0..SIZE as usize).map(|i| store.proof(i).unwrap().to_hex()));
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+        }
+        let file = std::fs::read_to_string(&path).unwrap();
+        let mut lines = file.lines();
+        let root = blake3::Hash::from_hex(lines.next().unwrap()).unwrap();
+        for (i, line) in lines.enumerate() {
+            let proof = Proof::from_hex(line).unwrap();
+            assert_eq!(proof.nth(), i);
+            assert_eq!(proof.layout(), layout);
+            assert_eq!(proof.size(), Some(SIZE as usize));
+            assert!(proof.prove_on(blake3::hash(&[i as u8])).against(root));
+            assert_eq!(store.proof(i).unwrap().to_hex(), line, "{} {}", name, i);
+        }
+        assert_eq!(store.root(), root);
+    }
+}
+
+#[test]
+// Bit reversed proofs encoded before they held the size still verify.
+pub fn legacy_golden_file() {
+    let file = std::fs::read_to_string(golden("bit_reversed_legacy")).unwrap();
+    let mut lines = file.lines();
+    let root = blake3::Hash::from_hex(lines.next().unwrap()).unwrap();
+    for (i, line) in lines.enumerate() {
+        let proof = Proof::from_hex(line).unwrap();
+        assert_eq!(proof.size(), None);
+        let hash = blake3::hash(&[i as u8]);
+        assert!(proof.prove_on(hash).against(root));
+        assert!(proof
+            .verify(hash, i, SIZE as usize, root, Layout::BitReversed)
+            .is_ok());
+    }
+}
+
+#[test]
+pub fn round_trip() {
+    for layout in [Layout::BitReversed, Layout::Rfc6962, Layout::Mmr] {
+        let store = store(layout);
+        for i in 0..SIZE as usize {
+            let proof = store.proof(i).unwrap();
+            assert_eq!(Proof::from_bytes(&proof.to_bytes()).unwrap(), proof);
+            assert_eq!(Proof::from_hex(&proof.to_hex()).unwrap(), proof);
+            assert_eq!(Proof::from_base64(&proof.to_base64()).unwrap(), proof);
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Envelope {
+    #[serde(with = "proof_hex")]
+    hex: Proof,
+    #[serde(with = "proof_base64")]
+    base64: Proof,
+}
+
+#[test]
+pub fn json_strings() {
+    let store = store(Layout::Mmr);
+    let envelope = Envelope {
+        hex: store.proof(3).unwrap(),
+        base64: store.proof(4).unwrap(),
+    };
+    let json = serde_json::to_value(&envelope).unwrap();
+    assert_eq!(json["hex"], store.proof(3).unwrap().to_hex());
+    assert_eq!(json["base64"], store.proof(4).unwrap().to_base64());
+    assert_eq!(serde_json::from_value::<Envelope>(json).unwrap(), envelope);
+    assert!(serde_json::from_str::<Envelope>(r#"{"hex": "zz", "base64": ""}"#).is_err());
+}
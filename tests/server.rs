@@ -0,0 +1,490 @@
+use reqwest::blocking::multipart::{Form, Part};
+use reqwest::blocking::{Client, Response};
+use reqwest::StatusCode;
+use std::path::Path;
+use std::process::Command;
+use std::{fs, thread};
+
+mod common;
+use common::{free_port, start_server, workdir};
+
+fn client(dir: &Path, port: u16) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_htree-client"));
+    cmd.current_dir(dir)
+        .arg("--state")
+        .arg(dir.join("roots.json"));
+    cmd.arg("127.0.0.1").arg(port.to_string());
+    cmd
+}
+
+// Push `content` to the store of root `root`, without checking the answer.
+fn push(port: u16, root: Option<&str>, content: &'static [u8]) -> Response {
+    let hash = blake3::hash(content).to_hex().to_string();
+    let form = Form::new().text("hash", hash).part(
+        "file",
+        Part::bytes(content)
+            .file_name("file")
+            .mime_str("application/octet-stream")
+            .unwrap(),
+    );
+    let req = Client::new()
+        .post(format!("http://127.0.0.1:{}", port))
+        .multipart(form);
+    match root {
+        Some(root) => req.query(&[("root", root)]),
+        None => req,
+    }
+    .send()
+    .unwrap()
+}
+
+#[test]
+// A store is only created by `POST /init`.
+pub fn init() {
+    let dir = workdir("init");
+    fs::write(dir.join("file"), b"first").unwrap();
+    let port = free_port();
+    let _server = start_server(&dir, port);
+
+    let res = push(port, None, b"first");
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert!(res.text().unwrap().contains("POST /init"));
+    let status = client(&dir, port).arg("push").arg("file").status().unwrap();
+    assert!(!status.success());
+
+    let status = client(&dir, port).arg("init").status().unwrap();
+    assert!(status.success());
+    let status = client(&dir, port).arg("init").status().unwrap();
+    assert!(!status.success());
+    let status = client(&dir, port).arg("push").arg("file").status().unwrap();
+    assert!(status.success());
+
+    // the store isn't empty anymore, a push must name its root.
+    let res = push(port, None, b"second");
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert!(res.text().unwrap().contains("1 elements"));
+}
+
+#[test]
+// An unknown root is a 404 telling the current root, not an empty store.
+pub fn unknown_root() {
+    let dir = workdir("unknown-root");
+    fs::write(dir.join("file"), b"content").unwrap();
+    let port = free_port();
+    let _server = start_server(&dir, port);
+    assert!(client(&dir, port).arg("init").status().unwrap().success());
+    assert!(client(&dir, port)
+        .arg("push")
+        .arg("file")
+        .status()
+        .unwrap()
+        .success());
+    let head: serde_json::Value = Client::new()
+        .get(format!("http://127.0.0.1:{}/head", port))
+        .send()
+        .unwrap()
+        .json()
+        .unwrap();
+    let root = head["root"].as_str().unwrap().to_owned();
+
+    let unknown = blake3::hash(b"unknown").to_hex().to_string();
+    for path in ["/0", "/0/proof"] {
+        let res = Client::new()
+            .get(format!("http://127.0.0.1:{}{}", port, path))
+            .query(&[("root", &unknown)])
+            .send()
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let body = res.text().unwrap();
+        assert!(
+            body.contains(&root) && body.contains("1 elements"),
+            "{}",
+            body
+        );
+    }
+    // a push only extends the latest store.
+    let res = push(port, Some(&unknown), b"other");
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    assert!(res.text().unwrap().contains(&root));
+
+    let res = Client::new()
+        .get(format!("http://127.0.0.1:{}/0", port))
+        .query(&[("root", "../HEAD")])
+        .send()
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = Client::new()
+        .get(format!("http://127.0.0.1:{}/0", port))
+        .query(&[("root", &root)])
+        .send()
+        .unwrap();
+    assert_eq!(res.bytes().unwrap().as_ref(), b"content");
+}
+
+#[test]
+// A push extends the store it names, never the one of a past size.
+pub fn push_at_size() {
+    let dir = workdir("push-at-size");
+    fs::write(dir.join("file"), b"content").unwrap();
+    let port = free_port();
+    let _server = start_server(&dir, port);
+    assert!(client(&dir, port).arg("init").status().unwrap().success());
+    assert!(client(&dir, port)
+        .arg("push")
+        .arg("file")
+        .status()
+        .unwrap()
+        .success());
+    let head = fs::read_to_string(dir.join("data").join("HEAD")).unwrap();
+
+    for query in [
+        vec![("size", "0")],
+        vec![("root", head.as_str()), ("size", "1")],
+    ] {
+        let form = Form::new().text("hash", blake3::hash(b"other").to_hex().to_string());
+        let res = Client::new()
+            .post(format!("http://127.0.0.1:{}", port))
+            .query(&query)
+            .multipart(form)
+            .send()
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+    assert_eq!(
+        fs::read_to_string(dir.join("data").join("HEAD")).unwrap(),
+        head
+    );
+
+    // the file and its proof are still served at a past size.
+    let res = Client::new()
+        .get(format!("http://127.0.0.1:{}/0/proof", port))
+        .query(&[("size", 1)])
+        .send()
+        .unwrap();
+    assert!(res.status().is_success());
+}
+
+#[test]
+// Bad uploads to the store are bad requests which leave it as is.
+pub fn push_errors() {
+    let dir = workdir("push-errors");
+    let port = free_port();
+    let _server = start_server(&dir, port);
+    assert!(client(&dir, port).arg("init").status().unwrap().success());
+    let file = || {
+        Part::bytes(&b"content"[..])
+            .file_name("file")
+            .mime_str("application/octet-stream")
+            .unwrap()
+    };
+    let forms = [
+        Form::new().part("file", file()),
+        Form::new().text("hash", "").part("file", file()),
+        Form::new().text("hash", blake3::hash(b"content").to_hex().to_string()),
+        Form::new()
+            .text("hash", blake3::hash(b"other").to_hex().to_string())
+            .part("file", file()),
+    ];
+    for form in forms {
+        let res = Client::new()
+            .post(format!("http://127.0.0.1:{}", port))
+            .multipart(form)
+            .send()
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+    let res = Client::new()
+        .post(format!("http://127.0.0.1:{}", port))
+        .header("content-type", "multipart/form-data; boundary=x")
+        .body("--x\r\nnot a part")
+        .send()
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        fs::read_to_string(dir.join("data").join("HEAD")).unwrap(),
+        ""
+    );
+
+    // the store still takes a good upload.
+    assert!(push(port, None, b"content").status().is_success());
+}
+
+#[test]
+// Of concurrent pushes extending the same store, only one is, the others conflict.
+pub fn concurrent_pushes() {
+    let dir = workdir("concurrent-pushes");
+    fs::write(dir.join("file"), b"content").unwrap();
+    let port = free_port();
+    let _server = start_server(&dir, port);
+    assert!(client(&dir, port).arg("init").status().unwrap().success());
+    assert!(client(&dir, port)
+        .arg("push")
+        .arg("file")
+        .status()
+        .unwrap()
+        .success());
+    let root = fs::read_to_string(dir.join("data").join("HEAD")).unwrap();
+
+    let pushes: Vec<_> = (0..8)
+        .map(|_| {
+            let root = root.clone();
+            thread::spawn(move || push(port, Some(&root), b"other").status())
+        })
+        .collect();
+    let statuses: Vec<_> = pushes.into_iter().map(|p| p.join().unwrap()).collect();
+    assert_eq!(statuses.iter().filter(|s| s.is_success()).count(), 1);
+    assert!(statuses
+        .iter()
+        .all(|s| s.is_success() || *s == StatusCode::CONFLICT));
+    let log = fs::read_to_string(dir.join("data").join("roots.log")).unwrap();
+    assert_eq!(log.lines().count(), 2);
+}
+
+#[test]
+// The roots kept by host in the working directory by older clients are adopted.
+pub fn legacy_roots() {
+    let dir = workdir("legacy-roots");
+    fs::write(dir.join("file"), b"first").unwrap();
+    let port = free_port();
+    let _server = start_server(&dir, port);
+    assert!(client(&dir, port).arg("init").status().unwrap().success());
+    assert!(client(&dir, port)
+        .arg("push")
+        .arg("file")
+        .status()
+        .unwrap()
+        .success());
+    let head = fs::read_to_string(dir.join("data").join("HEAD")).unwrap();
+
+    let old = dir.join("old");
+    fs::create_dir(&old).unwrap();
+    fs::write(old.join("file"), b"second").unwrap();
+    fs::write(
+        old.join("roots.json"),
+        format!(r#"{{"127.0.0.1":"{}"}}"#, head),
+    )
+    .unwrap();
+    let state = old.join("state").join("roots.json");
+    let old_client = || {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_htree-client"));
+        cmd.current_dir(&old)
+            .arg("--state")
+            .arg(&state)
+            .arg("127.0.0.1")
+            .arg(port.to_string());
+        cmd
+    };
+    assert!(old_client()
+        .arg("push")
+        .arg("file")
+        .status()
+        .unwrap()
+        .success());
+    let status = old_client().arg("status").output().unwrap();
+    assert!(String::from_utf8(status.stdout)
+        .unwrap()
+        .contains("Size: 2"));
+    assert!(state.exists());
+    assert!(!state.with_extension("json.tmp").exists());
+}
+
+#[test]
+// Each push is logged, and the client checks its verified roots against the log.
+pub fn roots() {
+    let dir = workdir("roots");
+    let port = free_port();
+    let _server = start_server(&dir, port);
+    assert!(client(&dir, port).arg("init").status().unwrap().success());
+    for i in 0..3 {
+        let file = format!("file{}", i);
+        fs::write(dir.join(&file), &file).unwrap();
+        assert!(client(&dir, port)
+            .arg("push")
+            .arg(&file)
+            .status()
+            .unwrap()
+            .success());
+    }
+    let page: serde_json::Value = Client::new()
+        .get(format!("http://127.0.0.1:{}/roots", port))
+        .query(&[("offset", 1), ("limit", 1)])
+        .send()
+        .unwrap()
+        .json()
+        .unwrap();
+    assert_eq!(page["total"], 3);
+    assert_eq!(page["roots"].as_array().unwrap().len(), 1);
+    assert_eq!(page["roots"][0]["size"], 2);
+
+    let output = client(&dir, port)
+        .arg("history")
+        .arg("--check")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let history = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(history.matches("logged by the server").count(), 3);
+}
+
+// Bind `content`, claimed to have the hash of `claimed`, to a key of the keyed store.
+fn put_key(port: u16, key: blake3::Hash, claimed: &[u8], content: &'static [u8]) -> Response {
+    let form = Form::new()
+        .text("hash", blake3::hash(claimed).to_hex().to_string())
+        .part(
+            "file",
+            Part::bytes(content)
+                .file_name("file")
+                .mime_str("application/octet-stream")
+                .unwrap(),
+        );
+    Client::new()
+        .put(format!("http://127.0.0.1:{}/keys/{}", port, key.to_hex()))
+        .multipart(form)
+        .send()
+        .unwrap()
+}
+
+#[test]
+// A file is only bound to a key under its own hash, and concurrent puts keep all the keys.
+pub fn keys() {
+    let dir = workdir("keys");
+    let port = free_port();
+    let _server = start_server(&dir, port);
+
+    let key = blake3::hash(b"key");
+    let res = put_key(port, key, b"other", b"content");
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let puts: Vec<_> = (0..8u8)
+        .map(|i| thread::spawn(move || put_key(port, blake3::hash(&[i]), b"content", b"content")))
+        .collect();
+    for put in puts {
+        assert!(put.join().unwrap().status().is_success());
+    }
+    let head: serde_json::Value = Client::new()
+        .get(format!("http://127.0.0.1:{}/keys", port))
+        .send()
+        .unwrap()
+        .json()
+        .unwrap();
+    assert_eq!(head["size"], 8);
+}
+
+#[test]
+// Bad uploads to a key are bad requests, and a keyed store which can't be read a server error.
+pub fn keys_errors() {
+    let dir = workdir("keys-errors");
+    let port = free_port();
+    let _server = start_server(&dir, port);
+    let url = format!(
+        "http://127.0.0.1:{}/keys/{}",
+        port,
+        blake3::hash(b"key").to_hex()
+    );
+    let file = || {
+        Part::bytes(&b"content"[..])
+            .file_name("file")
+            .mime_str("application/octet-stream")
+            .unwrap()
+    };
+    let forms = [
+        Form::new().part("file", file()),
+        Form::new().text("hash", "").part("file", file()),
+        Form::new().text("hash", blake3::hash(b"content").to_hex().to_string()),
+    ];
+    for form in forms {
+        let res = Client::new().put(&url).multipart(form).send().unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    fs::write(dir.join("data").join("keys.map"), b"{").unwrap();
+    for url in [format!("http://127.0.0.1:{}/keys", port), url + "/proof"] {
+        let res = Client::new().get(url).send().unwrap();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
+
+#[test]
+// A latest store which can't be read is a server error, not a crash of the request.
+pub fn missing_head() {
+    let dir = workdir("missing_head");
+    fs::write(dir.join("file"), b"content").unwrap();
+    let port = free_port();
+    let _server = start_server(&dir, port);
+    assert!(client(&dir, port).arg("init").status().unwrap().success());
+    assert!(client(&dir, port)
+        .arg("push")
+        .arg("file")
+        .status()
+        .unwrap()
+        .success());
+    let root = fs::read_to_string(dir.join("data").join("HEAD")).unwrap();
+    fs::remove_file(dir.join("data").join(format!("{}.store", root.trim()))).unwrap();
+
+    let res = Client::new()
+        .get(format!("http://127.0.0.1:{}/head", port))
+        .send()
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+// Run `htree-server fsck` in `dir`, returning whether it found no issue and its JSON report.
+fn fsck(dir: &Path) -> (bool, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_htree-server"))
+        .current_dir(dir)
+        .arg("fsck")
+        .arg("--json")
+        .output()
+        .unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+// Corrupt files are reported by `fsck`, as the leaves not giving the root of `HEAD`.
+pub fn fsck_corrupt() {
+    let dir = workdir("fsck-corrupt");
+    let port = free_port();
+    let server = start_server(&dir, port);
+    assert!(client(&dir, port).arg("init").status().unwrap().success());
+    for i in 0..2 {
+        let file = format!("file{}", i);
+        fs::write(dir.join(&file), &file).unwrap();
+        assert!(client(&dir, port)
+            .arg("push")
+            .arg(&file)
+            .status()
+            .unwrap()
+            .success());
+    }
+    drop(server);
+    assert!(fsck(&dir).0);
+
+    let data = dir.join("data");
+    let root = fs::read_to_string(data.join("HEAD")).unwrap();
+    let path = data.join(format!("{}.store", root.trim()));
+    let json = fs::read_to_string(&path).unwrap();
+    let mut store: serde_json::Value = serde_json::from_str(&json).unwrap();
+    store["tree"]["leaves"].as_array_mut().unwrap().swap(0, 1);
+    fs::write(&path, serde_json::to_vec(&store).unwrap()).unwrap();
+    let (clean, report) = fsck(&dir);
+    assert!(!clean);
+    assert!(
+        report.contains("root_mismatch") && report.contains("bad_proof"),
+        "{}",
+        report
+    );
+
+    fs::write(&path, &json[..json.len() / 2]).unwrap();
+    fs::write(data.join("keys.map"), b"{").unwrap();
+    let (clean, report) = fsck(&dir);
+    assert!(!clean);
+    assert!(
+        report.contains("corrupt_store") && report.contains("corrupt_keys"),
+        "{}",
+        report
+    );
+}
@@ -0,0 +1,107 @@
+use rcgen::{BasicConstraints, Certificate, CertificateParams, IsCa};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+mod common;
+use common::{free_port, server_command, spawn_server, workdir, Server};
+
+// Generate a CA, and a server and a client certificates signed by it, as PEM files in `dir`.
+fn gen_certs(dir: &Path) {
+    let mut params = CertificateParams::new(vec![]);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let ca = Certificate::from_params(params).unwrap();
+    fs::write(dir.join("ca.pem"), ca.serialize_pem().unwrap()).unwrap();
+
+    let server =
+        Certificate::from_params(CertificateParams::new(vec!["localhost".into()])).unwrap();
+    fs::write(
+        dir.join("server.pem"),
+        server.serialize_pem_with_signer(&ca).unwrap(),
+    )
+    .unwrap();
+    fs::write(dir.join("server.key"), server.serialize_private_key_pem()).unwrap();
+
+    let client = Certificate::from_params(CertificateParams::new(vec!["client".into()])).unwrap();
+    fs::write(
+        dir.join("client.pem"),
+        client.serialize_private_key_pem() + &client.serialize_pem_with_signer(&ca).unwrap(),
+    )
+    .unwrap();
+}
+
+fn start_server(dir: &Path, port: u16, client_ca: bool) -> Server {
+    let mut cmd = server_command(dir, port);
+    cmd.arg("--cert")
+        .arg(dir.join("server.pem"))
+        .arg("--key")
+        .arg(dir.join("server.key"));
+    if client_ca {
+        cmd.arg("--client-ca").arg(dir.join("ca.pem"));
+    }
+    spawn_server(&mut cmd, port)
+}
+
+fn client(dir: &Path, port: u16) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_htree-client"));
+    cmd.current_dir(dir)
+        .arg("--scheme")
+        .arg("https")
+        .arg("--ca-cert")
+        .arg(dir.join("ca.pem"))
+        .arg("--state")
+        .arg(dir.join("roots.json"));
+    cmd.arg("localhost").arg(port.to_string());
+    cmd
+}
+
+#[test]
+pub fn push_get_https() {
+    let dir = workdir("tls");
+    gen_certs(&dir);
+    fs::write(dir.join("file"), b"over tls").unwrap();
+    let port = free_port();
+    let _server = start_server(&dir, port, false);
+
+    let status = client(&dir, port).arg("init").status().unwrap();
+    assert!(status.success());
+    let status = client(&dir, port).arg("push").arg("file").status().unwrap();
+    assert!(status.success());
+    let status = client(&dir, port)
+        .arg("get")
+        .arg("0")
+        .arg("downloaded")
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert_eq!(fs::read(dir.join("downloaded")).unwrap(), b"over tls");
+}
+
+#[test]
+pub fn mtls() {
+    let dir = workdir("mtls");
+    gen_certs(&dir);
+    fs::write(dir.join("file"), b"over mtls").unwrap();
+    let port = free_port();
+    let _server = start_server(&dir, port, true);
+
+    // without a client certificate the handshake is refused.
+    let status = client(&dir, port).arg("push").arg("file").status().unwrap();
+    assert!(!status.success());
+
+    let status = client(&dir, port)
+        .arg("--identity")
+        .arg(dir.join("client.pem"))
+        .arg("init")
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let status = client(&dir, port)
+        .arg("--identity")
+        .arg(dir.join("client.pem"))
+        .arg("push")
+        .arg("file")
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
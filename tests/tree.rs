@@ -1,4 +1,5 @@
 use htree_challenge::tree::*;
+use proptest::prelude::*;
 
 #[test]
 pub fn insert() {
@@ -49,3 +50,419 @@ pub fn proof() {
         assert!(proof.prove_on(blake3::hash(&[di])).against(root.unwrap()))
     }
 }
+
+#[test]
+pub fn consistency() {
+    let mut store = HMap::new();
+    let mut roots = vec![None];
+    for i in 0u8..20u8 {
+        store.push(blake3::hash(&[i]), i);
+        roots.push(Some(store.root()));
+    }
+    let root = store.root();
+    for (from, old_root) in roots.iter().enumerate() {
+        let consistency = store.consistency(from).unwrap();
+        assert_eq!(consistency.from(), from);
+        assert_eq!(consistency.to(), 20);
+        assert!(consistency.verify(from, *old_root, root, Layout::BitReversed));
+        if from > 0 {
+            assert!(!consistency.verify(from, roots[from - 1], root, Layout::BitReversed));
+        }
+    }
+    assert!(store.consistency(21).is_none());
+}
+
+#[test]
+// A consistency proof from another size than the trusted one is rejected, even when it holds
+// the trusted root: the server can't pass the old root off as the one of another version.
+pub fn consistency_from() {
+    for layout in [Layout::BitReversed, Layout::Rfc6962, Layout::Mmr] {
+        let mut store = HMap::with_layout(layout);
+        let mut roots = vec![None];
+        for i in 0u8..20u8 {
+            store.push(blake3::hash(&[i]), i);
+            roots.push(Some(store.root()));
+        }
+        let root = store.root();
+        for (from, old_root) in roots.iter().enumerate() {
+            let consistency = store.consistency(from).unwrap();
+            assert!(consistency.verify(from, *old_root, root, layout));
+            for size in [from.wrapping_sub(1), from + 1] {
+                assert!(
+                    !consistency.verify(size, *old_root, root, layout),
+                    "{:?}",
+                    layout
+                );
+            }
+        }
+    }
+}
+
+#[test]
+// A consistency proof is checked in the expected layout, not in the one it claims.
+pub fn consistency_layout() {
+    let layouts = [Layout::BitReversed, Layout::Rfc6962, Layout::Mmr];
+    for layout in layouts {
+        let mut store = HMap::with_layout(layout);
+        let mut roots = vec![None];
+        for i in 0u8..20u8 {
+            store.push(blake3::hash(&[i]), i);
+            roots.push(Some(store.root()));
+        }
+        let root = store.root();
+        for (from, old_root) in roots.iter().enumerate() {
+            let consistency = store.consistency(from).unwrap();
+            assert_eq!(consistency.layout(), layout);
+            for other in layouts {
+                assert_eq!(
+                    consistency.verify(from, *old_root, root, other),
+                    other == layout
+                );
+            }
+        }
+    }
+}
+
+#[test]
+// A consistency proof grows with the elements pushed since `from`, not with the ones before, and
+// it's bounded from the empty store.
+pub fn consistency_size() {
+    let hashes: Vec<_> = (0..1usize << 12)
+        .map(|i| blake3::hash(&i.to_le_bytes()))
+        .collect();
+    let size = hashes.len();
+    // a hex hash is 67 bytes of JSON, a proof holds at most 2 log n of them.
+    let bound = |pushed: usize| (pushed.max(1) * 2 * 12 + 2) * 67;
+    for layout in [Layout::BitReversed, Layout::Rfc6962, Layout::Mmr] {
+        let store = HMap::from_leaves(layout, hashes.iter().map(|h| (*h, ())));
+        for from in [0, size / 2, size - 10, size - 1, size] {
+            let consistency = store.consistency(from).unwrap();
+            let pushed = if from == 0 { 0 } else { size - from };
+            let json = serde_json::to_vec(&consistency).unwrap();
+            assert!(json.len() <= bound(pushed), "{:?} from {}", layout, from);
+            let old_root = layout.root_of(&hashes[..from]);
+            assert!(consistency.verify(from, old_root, store.root(), layout));
+        }
+    }
+}
+
+#[test]
+pub fn root_of() {
+    for layout in [Layout::BitReversed, Layout::Rfc6962, Layout::Mmr] {
+        let mut store = HMap::with_layout(layout);
+        let mut hashes = vec![];
+        assert_eq!(layout.root_of(&hashes), None);
+        for i in 0u8..20u8 {
+            let hi = blake3::hash(&[i]);
+            store.push(hi, i);
+            hashes.push(hi);
+            assert_eq!(layout.root_of(&hashes), Some(store.root()));
+        }
+    }
+}
+
+#[test]
+pub fn layouts() {
+    for layout in [Layout::BitReversed, Layout::Rfc6962, Layout::Mmr] {
+        let mut store = HMap::with_layout(layout);
+        let mut roots = vec![None];
+        for i in 0u8..20u8 {
+            let hi = blake3::hash(&[i]);
+            let proof = store.push(hi, i);
+            assert_eq!(proof.layout(), layout);
+            assert_eq!(proof, store.proof(i as usize).unwrap());
+            assert_eq!(proof.hash(), *roots.last().unwrap());
+            assert_eq!(*proof.prove_on(hi), store.root());
+            roots.push(Some(store.root()));
+        }
+        let root = store.root();
+        for i in 0..20 {
+            let proof = store.proof(i).unwrap();
+            assert_eq!(store.get_hash(i), Some(blake3::hash(&[i as u8])));
+            assert!(proof.prove_on(blake3::hash(&[i as u8])).against(root));
+            assert!(!proof.prove_on(blake3::hash(&[i as u8 + 1])).against(root));
+        }
+        for (from, old_root) in roots.iter().enumerate() {
+            let consistency = store.consistency(from).unwrap();
+            assert_eq!(consistency.to(), 20);
+            assert!(consistency.verify(from, *old_root, root, layout));
+            if from > 0 {
+                assert!(!consistency.verify(from, roots[from - 1], root, layout));
+            }
+        }
+
+        // the store is saved and loaded back.
+        let json = serde_json::to_string(&store).unwrap();
+        let loaded: HMap<u8> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.root(), root);
+        assert_eq!(loaded.proof(7), store.proof(7));
+    }
+}
+
+#[test]
+// Every element is proven against every past root.
+pub fn proof_at() {
+    for layout in [Layout::BitReversed, Layout::Rfc6962, Layout::Mmr] {
+        let mut store = HMap::with_layout(layout);
+        let mut roots = vec![];
+        for i in 0u8..20u8 {
+            store.push(blake3::hash(&[i]), i);
+            roots.push(store.root());
+        }
+        for (size, root) in (1..=20).zip(roots) {
+            for nth in 0..size {
+                let proof = store.proof_at(nth, size).unwrap();
+                assert!(proof
+                    .verify(blake3::hash(&[nth as u8]), nth, size, root, layout)
+                    .is_ok());
+            }
+            assert_eq!(store.proof_at(size, size), None);
+        }
+        assert_eq!(store.proof_at(3, 21), None);
+        assert_eq!(store.proof_at(3, 20), store.proof(3));
+    }
+}
+
+#[test]
+// Elements hashed by the store are checked back after a round trip through JSON.
+pub fn items() {
+    let mut store = HMap::with_layout(Layout::Mmr);
+    for i in 0..8 {
+        let proof = store.push_item(format!("item {}", i));
+        assert!(proof
+            .prove_on(blake3::hash(format!("item {}", i).as_bytes()))
+            .against(store.root()));
+    }
+    let mut json = serde_json::to_value(&store).unwrap();
+    let loaded: HMap<String> = serde_json::from_value(json.clone()).unwrap();
+    assert!((0..8).all(|i| loaded.verify_item(i)));
+    json["data"][3] = "tampered".into();
+    let tampered: HMap<String> = serde_json::from_value(json).unwrap();
+    assert!(!tampered.verify_item(3));
+    assert!(tampered.verify_item(4));
+}
+
+#[test]
+pub fn smap() {
+    let mut map = SMap::new();
+    let key = |i: u8| blake3::hash(format!("file{}", i).as_bytes());
+    for i in 0u8..20u8 {
+        let hi = blake3::hash(&[i]);
+        let proof = map.insert(key(i), hi, i);
+        assert_eq!(proof.key(), key(i));
+        assert!(proof.verify(Some(hi), map.root()));
+    }
+    let root = map.root();
+    for i in 0u8..20u8 {
+        assert_eq!(map.get(&key(i)), Some(i));
+        assert!(map.proof(key(i)).verify(Some(blake3::hash(&[i])), root));
+    }
+    for i in 20u8..40u8 {
+        assert_eq!(map.get(&key(i)), None);
+        assert!(map.proof(key(i)).verify(None, root));
+        assert!(!map.proof(key(i)).verify(Some(blake3::hash(&[i])), root));
+    }
+
+    // the proofs and the map are saved and loaded back.
+    let proof: KeyProof =
+        serde_json::from_str(&serde_json::to_string(&map.proof(key(3))).unwrap()).unwrap();
+    assert!(proof.verify(Some(blake3::hash(&[3])), root));
+    let loaded: SMap<u8> = serde_json::from_str(&serde_json::to_string(&map).unwrap()).unwrap();
+    assert_eq!(loaded.root(), root);
+}
+
+// Replace the hex strings of hashes by arrays of bytes, as older versions wrote them.
+fn legacy(json: serde_json::Value) -> serde_json::Value {
+    match json {
+        serde_json::Value::String(s) => match blake3::Hash::from_hex(&s) {
+            Ok(hash) => serde_json::json!(hash.as_bytes()),
+            Err(_) => serde_json::Value::String(s),
+        },
+        serde_json::Value::Array(a) => a.into_iter().map(legacy).collect(),
+        serde_json::Value::Object(o) => o.into_iter().map(|(k, v)| (k, legacy(v))).collect(),
+        json => json,
+    }
+}
+
+#[test]
+pub fn json() {
+    for layout in [Layout::BitReversed, Layout::Rfc6962, Layout::Mmr] {
+        let mut store = HMap::with_layout(layout);
+        for i in 0u8..7u8 {
+            store.push(blake3::hash(&[i]), i);
+        }
+        let root = store.root();
+
+        let json = serde_json::to_value(&store).unwrap();
+        let loaded: HMap<u8> = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(loaded.root(), root);
+        let loaded: HMap<u8> = serde_json::from_value(legacy(json)).unwrap();
+        assert_eq!(loaded.root(), root);
+
+        let proof = store.proof(5).unwrap();
+        let json = serde_json::to_value(&proof).unwrap();
+        assert!(json["hashes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|h| h.is_string()));
+        assert_eq!(
+            serde_json::from_value::<Proof>(json.clone()).unwrap(),
+            proof
+        );
+        assert_eq!(
+            serde_json::from_value::<Proof>(legacy(json)).unwrap(),
+            proof
+        );
+
+        let consistency = store.consistency(2).unwrap();
+        let json = serde_json::to_value(&consistency).unwrap();
+        let loaded: Consistency = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(loaded, consistency);
+        let loaded: Consistency = serde_json::from_value(legacy(json)).unwrap();
+        assert_eq!(loaded, consistency);
+    }
+}
+
+#[test]
+pub fn proofs() {
+    for layout in [Layout::BitReversed, Layout::Rfc6962, Layout::Mmr] {
+        let store = HMap::from_leaves(layout, (0u8..50).map(|i| (blake3::hash(&[i]), i)));
+        let indices = [0, 7, 49, 50, 7];
+        let proofs: Vec<_> = indices.iter().map(|nth| store.proof(*nth)).collect();
+        assert_eq!(store.proofs(&indices), proofs);
+    }
+}
+
+#[test]
+// Big enough to be hashed in parallel with the `parallel` feature.
+pub fn large() {
+    let hashes: Vec<_> = (0..20_000u32)
+        .map(|i| blake3::hash(&i.to_le_bytes()))
+        .collect();
+    for layout in [Layout::BitReversed, Layout::Rfc6962, Layout::Mmr] {
+        let store = HMap::from_leaves(layout, hashes.iter().map(|h| (*h, ())));
+        let root = store.root();
+        assert_eq!(layout.root_of(&hashes), Some(root));
+        let proofs = store.proofs(&[0, 12_345, 19_999]);
+        for (nth, proof) in [0, 12_345, 19_999].into_iter().zip(proofs) {
+            assert!(proof
+                .unwrap()
+                .verify(hashes[nth], nth, hashes.len(), root, layout)
+                .is_ok());
+        }
+    }
+}
+
+fn layout() -> impl Strategy<Value = Layout> {
+    prop_oneof![
+        Just(Layout::BitReversed),
+        Just(Layout::Rfc6962),
+        Just(Layout::Mmr)
+    ]
+}
+
+// Random elements of random sizes, duplicates included.
+fn elements() -> impl Strategy<Value = Vec<Vec<u8>>> {
+    prop::collection::vec(prop::collection::vec(any::<u8>(), 0..8), 0..48)
+}
+
+proptest! {
+    // each case pushes hundreds of elements to compare with, so keep them few.
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    // A store built from its leaves is the one pushing them gives.
+    fn from_leaves(size in 0usize..600, layout in layout()) {
+        let leaves = (0..size).map(|i| (blake3::hash(&i.to_le_bytes()), i));
+        let built = HMap::from_leaves(layout, leaves.clone());
+        let mut pushed = HMap::with_layout(layout);
+        pushed.extend(leaves);
+        prop_assert_eq!(
+            serde_json::to_value(&built).unwrap(),
+            serde_json::to_value(&pushed).unwrap()
+        );
+        if size > 0 {
+            prop_assert_eq!(built.root(), pushed.root());
+            prop_assert_eq!(built.proof(size / 2), pushed.proof(size / 2));
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    // Every push returns the proof of the element, which proves the previous root was the one
+    // of the store and gives the new root.
+    fn pushes(layout in layout(), elements in elements()) {
+        let mut store = HMap::with_layout(layout);
+        let mut old_root = None;
+        for (nth, element) in elements.iter().enumerate() {
+            let hash = blake3::hash(element);
+            let proof = store.push(hash, element.clone());
+            prop_assert_eq!(&store.proof(nth).unwrap(), &proof);
+            prop_assert_eq!(proof.hash(), old_root);
+            let verified = proof.verify_append(hash, nth, old_root, layout).unwrap();
+            prop_assert_eq!(verified.root(), store.root());
+            old_root = Some(store.root());
+        }
+    }
+
+    #[test]
+    // Every element is proven against the root, and only itself is.
+    fn proofs_verify(layout in layout(), elements in elements()) {
+        let hashes: Vec<_> = elements.iter().map(|e| blake3::hash(e)).collect();
+        let store = HMap::from_leaves(layout, hashes.iter().copied().zip(elements.clone()));
+        prop_assume!(!store.is_empty());
+        let (root, size) = (store.root(), store.len());
+        for (nth, hash) in hashes.iter().enumerate() {
+            let proof = store.proof(nth).unwrap();
+            prop_assert!(proof.prove_on(*hash).against(root));
+            prop_assert!(proof.verify(*hash, nth, size, root, layout).is_ok());
+            let other = blake3::hash(&[elements[nth].as_slice(), b"other"].concat());
+            prop_assert!(proof.verify(other, nth, size, root, layout).is_err());
+            if size > 1 {
+                let shifted = (nth + 1) % size;
+                prop_assert!(proof.verify(*hash, shifted, size, root, layout).is_err());
+            }
+        }
+        prop_assert!(store.proof(size).is_none());
+    }
+
+    #[test]
+    // A store, its proofs and its consistency proofs read back from JSON give the same roots.
+    fn serde_round_trip(
+        layout in layout(),
+        elements in elements(),
+        from in any::<prop::sample::Index>(),
+    ) {
+        let mut store = HMap::with_layout(layout);
+        store.extend(elements.iter().map(|e| (blake3::hash(e), e.clone())));
+        prop_assume!(!store.is_empty());
+        let root = store.root();
+
+        let json = serde_json::to_string(&store).unwrap();
+        let loaded: HMap<Vec<u8>> = serde_json::from_str(&json).unwrap();
+        prop_assert_eq!(loaded.root(), root);
+        prop_assert_eq!(loaded.len(), store.len());
+        prop_assert_eq!(serde_json::to_string(&loaded).unwrap(), json);
+
+        let nth = from.index(store.len());
+        let proof = store.proof(nth).unwrap();
+        let read: Proof = serde_json::from_str(&serde_json::to_string(&proof).unwrap()).unwrap();
+        prop_assert_eq!(&read, &proof);
+        prop_assert_eq!(&Proof::from_bytes(&proof.to_bytes()).unwrap(), &proof);
+        prop_assert!(read.prove_on(blake3::hash(&elements[nth])).against(root));
+
+        let old_root = (nth > 0).then(|| layout.root_of(&store_hashes(&store, nth)).unwrap());
+        let consistency = store.consistency(nth).unwrap();
+        let read: Consistency =
+            serde_json::from_str(&serde_json::to_string(&consistency).unwrap()).unwrap();
+        prop_assert_eq!(&read, &consistency);
+        prop_assert!(read.verify(nth, old_root, root, layout));
+    }
+}
+
+// The hashes of the first `size` elements of a store.
+fn store_hashes<D>(store: &HMap<D>, size: usize) -> Vec<blake3::Hash> {
+    (0..size).map(|i| store.get_hash(i).unwrap()).collect()
+}